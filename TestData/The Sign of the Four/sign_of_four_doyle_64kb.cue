@@ -0,0 +1,18 @@
+REM GENRE Audiobook
+REM DATE 1890
+PERFORMER "Mark F. Smith"
+TITLE "The Sign of the Four"
+FILE "sign_of_four_doyle_64kb.mp3" MP3
+  TRACK 01 AUDIO
+    TITLE "The Science of Deduction"
+    PERFORMER "Mark F. Smith"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "The Statement of the Case"
+    PERFORMER "Mark F. Smith"
+    INDEX 01 00:10:00
+  TRACK 03 AUDIO
+    TITLE "In Quest of a Solution"
+    PERFORMER "Mark F. Smith"
+    INDEX 00 00:19:50
+    INDEX 01 00:20:37
@@ -0,0 +1,102 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read},
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+use tracing::warn;
+use zip::ZipArchive;
+
+use crate::{merge_books, parse_reader, warn_incomplete, Book, ParseError, ParseOptions};
+
+/// Parses the audio files inside a zip archive and merges them into one [`Book`], without
+/// extracting them.
+///
+/// The tracks are named like the entry inside the archive, `Book.zip/01.mp3`. Entries that
+/// can't be parsed are skipped with a warning like in [`crate::parse_book`], `None` means
+/// no entry could be parsed.
+pub fn parse_book_from_zip(path: &Path) -> Option<Result<Book>> {
+    let options = ParseOptions::default();
+    let mut archive = match File::open(path)
+        .map_err(|e| ParseError::unreadable(path, e))
+        .and_then(|file| {
+            ZipArchive::new(BufReader::new(file)).map_err(|e| ParseError::unreadable(path, e))
+        }) {
+        Ok(archive) => archive,
+        Err(e) => return Some(Err(e.into())),
+    };
+
+    let mut names: Vec<String> = archive
+        .file_names()
+        // names that aren't valid UTF-8 can't be looked up again
+        .filter_map(|name| name.ok())
+        .filter(|name| !name.ends_with('/'))
+        .filter(|name| {
+            let entry = Path::new(name.as_ref());
+            let hidden = entry
+                .file_name()
+                .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+            options.has_audio_extension(entry) && !(options.skip_hidden && hidden)
+        })
+        .map(|name| name.into_owned())
+        .collect();
+    names.sort();
+
+    let mut books = Vec::new();
+    for name in names {
+        let entry_path = path.join(&name);
+        // entries are compressed streams, the tag readers need to seek
+        let mut data = Vec::new();
+        let read = archive
+            .by_name(&name)
+            .map_err(|e| ParseError::unreadable(&entry_path, e))
+            .and_then(|mut entry| {
+                entry
+                    .read_to_end(&mut data)
+                    .map_err(|e| ParseError::unreadable(&entry_path, e))
+            })
+            .and_then(|_| parse_reader(Cursor::new(data), &entry_path, &options));
+        match read {
+            Ok(book) => books.push(book),
+            Err(e) => warn!("Error parsing: {:?}", e),
+        }
+    }
+
+    match merge_books(books, &options) {
+        Ok(Some(book)) => {
+            warn_incomplete(&book);
+            Some(Ok(book))
+        }
+        Ok(None) => None,
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_book_from_zip() {
+        let book = parse_book_from_zip(Path::new("../TestData/The Jungle Book.zip"))
+            .unwrap()
+            .unwrap();
+        let unzipped = crate::parse_book("../TestData/The Jungle Book")
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.content_hash(), unzipped.content_hash());
+        assert_eq!(book.replaygain_album, Some(-6.48));
+        assert_eq!(
+            book.tracks[0].path,
+            Path::new("../TestData/The Jungle Book.zip/junglebook_01_kipling_64kb.mp3")
+        );
+
+        assert!(
+            parse_book_from_zip(Path::new("../TestData/Huckfinn/missing.zip"))
+                .unwrap()
+                .is_err()
+        );
+    }
+}
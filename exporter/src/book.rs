@@ -0,0 +1,1817 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{BuildError, ParseError};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Track {
+    pub(crate) title: String,
+    pub(crate) reader: Vec<String>,
+    pub(crate) track: u32,
+    pub(crate) disc: Option<u32>,
+    #[serde(
+        serialize_with = "serialize_seconds",
+        deserialize_with = "deserialize_seconds"
+    )]
+    pub(crate) duration: Option<Duration>,
+    /// The average bitrate of the audio stream.
+    pub(crate) bitrate_kbps: Option<u32>,
+    pub(crate) sample_rate_hz: Option<u32>,
+    /// The ReplayGain adjustment of the track in dB.
+    pub(crate) replaygain_track: Option<f32>,
+    /// The text spoken in the track, from the lyrics tag. Left out of the serialized output
+    /// if unknown, as it's rarely there and can be long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) transcript: Option<String>,
+    /// The software that encoded the file, like `LAME 3.100`. Tracks of a book may differ,
+    /// it's not checked while merging.
+    pub(crate) encoder: Option<String>,
+    /// The work of an omnibus edition the track belongs to, like `The Two Towers`, from the
+    /// movement name iTunes uses for classical works. Left out of the serialized output if
+    /// unknown, like all of the movement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) movement: Option<String>,
+    /// The number of that work within the omnibus.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) movement_index: Option<u32>,
+    /// The file the track was read from, left out of the serialized output as it depends
+    /// on where the library is mounted.
+    #[serde(skip)]
+    pub(crate) path: PathBuf,
+}
+
+/// Serialized with the readers of its tracks after the authors, see [`Book::reader`].
+#[derive(Debug, Deserialize, Clone)]
+pub struct Book {
+    pub(crate) title: String,
+    pub(crate) author: BTreeSet<String>,
+    pub(crate) tracks: Vec<Track>,
+    /// The number of files found.
+    pub(crate) total_tracks: u32,
+    /// The number of tracks according to the tags.
+    pub(crate) expected_tracks: Option<u32>,
+    #[serde(
+        serialize_with = "serialize_seconds",
+        deserialize_with = "deserialize_seconds"
+    )]
+    pub(crate) total_duration: Option<Duration>,
+    /// The ReplayGain adjustment of the whole book in dB, only set if all files agree.
+    pub(crate) replaygain_album: Option<f32>,
+    /// The rating of 1 to 5 stars a player stored, the best of all tracks.
+    pub(crate) rating: Option<u8>,
+    /// Whether the tracks are meant to be played without gaps, only set if all files agree.
+    pub(crate) gapless: Option<bool>,
+    /// Whether the recording is abridged, from an `ABRIDGED` tag or the title.
+    pub(crate) abridged: Option<bool>,
+    pub(crate) discs: Option<u32>,
+    /// The names of single discs of a box set, ordered by disc number.
+    pub(crate) disc_subtitles: Vec<(u32, String)>,
+    /// The year of the recording.
+    pub(crate) year: Option<i32>,
+    /// The year the book was first published, like 1884 for Huckleberry Finn.
+    pub(crate) original_year: Option<i32>,
+    pub(crate) genre: Option<String>,
+    /// ISO 639-2 code like `eng`.
+    pub(crate) language: Option<String>,
+    pub(crate) publisher: Option<String>,
+    /// ISBN-10 or ISBN-13 without hyphens.
+    pub(crate) isbn: Option<String>,
+    /// Amazon's identifier, common for Audible books.
+    pub(crate) asin: Option<String>,
+    pub(crate) series: Option<String>,
+    pub(crate) series_index: Option<f32>,
+    /// The synopsis of the book.
+    pub(crate) description: Option<String>,
+    pub(crate) comments: Vec<String>,
+    pub(crate) cover: Option<Cover>,
+    /// The chapter marks of a single file book, empty for books split into several files.
+    pub(crate) chapters: Vec<Chapter>,
+    /// How often each value was seen in the merged files, used by
+    /// [`MergeStrategy::PreferMostCommon`].
+    #[serde(skip)]
+    pub(crate) votes: Votes,
+}
+
+/// A chapter mark inside a single file.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Chapter {
+    pub(crate) title: String,
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub(crate) start: Duration,
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub(crate) end: Duration,
+}
+
+/// How [`Book::merge_with`] resolves parts disagreeing on discs, year or genre.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Fail with an error, the behavior of [`Book::merge`].
+    #[default]
+    Strict,
+    /// Keep the value of the left book.
+    PreferFirst,
+    /// Keep the value seen in the most files, ties keep the left value.
+    PreferMostCommon,
+}
+
+/// How [`Book::has_author`] compares names, both ignore case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameMatch {
+    /// The whole name must match.
+    #[default]
+    Exact,
+    /// The name may be part of an author, `twain` matches `Mark Twain`.
+    Substring,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub(crate) struct Votes {
+    pub(crate) discs: BTreeMap<Option<u32>, u32>,
+    pub(crate) year: BTreeMap<Option<i32>, u32>,
+    pub(crate) genre: BTreeMap<Option<String>, u32>,
+}
+
+/// The votes only record how a book was merged, two books with the same metadata are equal.
+impl PartialEq for Book {
+    fn eq(&self, other: &Self) -> bool {
+        let Book {
+            title,
+            author,
+            tracks,
+            total_tracks,
+            expected_tracks,
+            total_duration,
+            replaygain_album,
+            rating,
+            gapless,
+            abridged,
+            discs,
+            disc_subtitles,
+            year,
+            original_year,
+            genre,
+            language,
+            publisher,
+            isbn,
+            asin,
+            series,
+            series_index,
+            description,
+            comments,
+            cover,
+            chapters,
+            votes: _,
+        } = self;
+        *title == other.title
+            && *author == other.author
+            && *tracks == other.tracks
+            && *total_tracks == other.total_tracks
+            && *expected_tracks == other.expected_tracks
+            && *total_duration == other.total_duration
+            && *replaygain_album == other.replaygain_album
+            && *rating == other.rating
+            && *gapless == other.gapless
+            && *abridged == other.abridged
+            && *discs == other.discs
+            && *disc_subtitles == other.disc_subtitles
+            && *year == other.year
+            && *original_year == other.original_year
+            && *genre == other.genre
+            && *language == other.language
+            && *publisher == other.publisher
+            && *isbn == other.isbn
+            && *asin == other.asin
+            && *series == other.series
+            && *series_index == other.series_index
+            && *description == other.description
+            && *comments == other.comments
+            && *cover == other.cover
+            && *chapters == other.chapters
+    }
+}
+
+impl Serialize for Book {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Book {
+            title,
+            author,
+            tracks,
+            total_tracks,
+            expected_tracks,
+            total_duration,
+            replaygain_album,
+            rating,
+            gapless,
+            abridged,
+            discs,
+            disc_subtitles,
+            year,
+            original_year,
+            genre,
+            language,
+            publisher,
+            isbn,
+            asin,
+            series,
+            series_index,
+            description,
+            comments,
+            cover,
+            chapters,
+            votes: _,
+        } = self;
+        let mut state = serializer.serialize_struct("Book", 26)?;
+        state.serialize_field("title", title)?;
+        state.serialize_field("author", author)?;
+        state.serialize_field("reader", &self.reader())?;
+        state.serialize_field("tracks", tracks)?;
+        state.serialize_field("total_tracks", total_tracks)?;
+        state.serialize_field("expected_tracks", expected_tracks)?;
+        state.serialize_field("total_duration", &total_duration.map(|d| d.as_secs_f64()))?;
+        state.serialize_field("replaygain_album", replaygain_album)?;
+        state.serialize_field("rating", rating)?;
+        state.serialize_field("gapless", gapless)?;
+        state.serialize_field("abridged", abridged)?;
+        state.serialize_field("discs", discs)?;
+        state.serialize_field("disc_subtitles", disc_subtitles)?;
+        state.serialize_field("year", year)?;
+        state.serialize_field("original_year", original_year)?;
+        state.serialize_field("genre", genre)?;
+        state.serialize_field("language", language)?;
+        state.serialize_field("publisher", publisher)?;
+        state.serialize_field("isbn", isbn)?;
+        state.serialize_field("asin", asin)?;
+        state.serialize_field("series", series)?;
+        state.serialize_field("series_index", series_index)?;
+        state.serialize_field("description", description)?;
+        state.serialize_field("comments", comments)?;
+        state.serialize_field("cover", cover)?;
+        state.serialize_field("chapters", chapters)?;
+        state.end()
+    }
+}
+
+/// The value of `res`, its error is added to `errors` instead.
+fn collect_conflict<T>(res: Result<T, ParseError>, errors: &mut Vec<ParseError>) -> Option<T> {
+    res.map_err(|e| errors.push(e)).ok()
+}
+
+/// The part of `title` after the `book` title and a leading track number.
+fn clean_track_title<'a>(title: &'a str, book: &str) -> &'a str {
+    let is_separator = |c: char| c.is_whitespace() || matches!(c, '-' | '_' | ':' | '.');
+    let mut title = title.trim();
+    if let Some(prefix) = title.get(..book.len()) {
+        let rest = &title[book.len()..];
+        let whole_words = rest.is_empty() || rest.starts_with(is_separator);
+        if !book.is_empty() && whole_words && prefix.to_lowercase() == book.to_lowercase() {
+            title = rest.trim_start_matches(is_separator);
+        }
+    }
+    // only a number followed by a separator is a prefix, `1984` or `12 Angry Men` stay
+    let number = title.trim_start_matches(|c: char| c.is_ascii_digit());
+    if number.len() < title.len() {
+        let rest = number.trim_start_matches(is_separator);
+        let separator = &number[..number.len() - rest.len()];
+        if separator.contains(|c: char| !c.is_whitespace()) {
+            title = rest;
+        }
+    }
+    title
+}
+
+/// The words of a name in lower case, for comparing names written differently.
+fn name_key(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Resolves a conflict between `lhs` and `rhs` and merges their vote counts.
+///
+/// A book that never took part in a merge has no votes yet, its own value counts once.
+fn resolve<T: Ord + Clone>(
+    strategy: MergeStrategy,
+    (lhs, mut lhs_votes): (T, BTreeMap<T, u32>),
+    (rhs, rhs_votes): (T, BTreeMap<T, u32>),
+    mismatch: impl FnOnce(T, T) -> ParseError,
+) -> Result<(T, BTreeMap<T, u32>), ParseError> {
+    if lhs_votes.is_empty() {
+        lhs_votes.insert(lhs.clone(), 1);
+    }
+    let rhs_votes = if rhs_votes.is_empty() {
+        BTreeMap::from([(rhs.clone(), 1)])
+    } else {
+        rhs_votes
+    };
+    for (value, count) in rhs_votes {
+        *lhs_votes.entry(value).or_default() += count;
+    }
+
+    let value = match strategy {
+        _ if lhs == rhs => lhs,
+        MergeStrategy::Strict => return Err(mismatch(lhs, rhs)),
+        MergeStrategy::PreferFirst => lhs,
+        MergeStrategy::PreferMostCommon => {
+            let lhs_count = lhs_votes[&lhs];
+            lhs_votes
+                .iter()
+                .filter(|(_, &count)| count > lhs_count)
+                .max_by_key(|(_, &count)| count)
+                .map_or(lhs, |(value, _)| value.clone())
+        }
+    };
+    Ok((value, lhs_votes))
+}
+
+impl Track {
+    /// Starts building a track by hand, title and track number are always needed.
+    pub fn builder(title: impl Into<String>, track: u32) -> TrackBuilder {
+        TrackBuilder {
+            track: Track {
+                title: title.into(),
+                reader: Vec::new(),
+                track,
+                disc: None,
+                duration: None,
+                bitrate_kbps: None,
+                sample_rate_hz: None,
+                replaygain_track: None,
+                transcript: None,
+                encoder: None,
+                movement: None,
+                movement_index: None,
+                path: PathBuf::new(),
+            },
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The readers of this track, books read by several people list different ones.
+    pub fn reader(&self) -> &[String] {
+        &self.reader
+    }
+
+    /// The track number, restarting on every disc.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    pub fn disc(&self) -> Option<u32> {
+        self.disc
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// The average bitrate of the audio stream.
+    pub fn bitrate_kbps(&self) -> Option<u32> {
+        self.bitrate_kbps
+    }
+
+    pub fn sample_rate_hz(&self) -> Option<u32> {
+        self.sample_rate_hz
+    }
+
+    /// The ReplayGain adjustment in dB.
+    pub fn replaygain_track(&self) -> Option<f32> {
+        self.replaygain_track
+    }
+
+    /// The text spoken in the track.
+    pub fn transcript(&self) -> Option<&str> {
+        self.transcript.as_deref()
+    }
+
+    /// The software that encoded the file, to tell re-encodes apart.
+    pub fn encoder(&self) -> Option<&str> {
+        self.encoder.as_deref()
+    }
+
+    /// The work of an omnibus edition the track belongs to.
+    pub fn movement(&self) -> Option<&str> {
+        self.movement.as_deref()
+    }
+
+    /// The number of the work within the omnibus, `2` for the second book of a trilogy.
+    pub fn movement_index(&self) -> Option<u32> {
+        self.movement_index
+    }
+
+    /// The file the track was read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the track belongs in the book, discs restart the track numbering.
+    pub(crate) fn position(&self) -> (u32, u32) {
+        (self.disc.unwrap_or(0), self.track)
+    }
+}
+
+impl Chapter {
+    pub fn new(title: impl Into<String>, start: Duration, end: Duration) -> Self {
+        Chapter {
+            title: title.into(),
+            start,
+            end,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The start, relative to the start of the file.
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    pub fn end(&self) -> Duration {
+        self.end
+    }
+}
+
+/// An embedded cover image.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Cover {
+    pub(crate) mime_type: String,
+    /// The raw image, left out of the serialized output to keep it readable.
+    #[serde(skip)]
+    pub(crate) data: Vec<u8>,
+}
+
+impl Cover {
+    pub fn new(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+        Cover {
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    /// The image type, like `image/jpeg`.
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The file extension for the image, `jpg` unless it's a PNG.
+    pub(crate) fn extension(&self) -> &'static str {
+        match self.mime_type.as_str() {
+            "image/png" => "png",
+            _ => "jpg",
+        }
+    }
+}
+
+impl Book {
+    /// Starts building a book by hand, for tools and tests that don't read files.
+    pub fn builder() -> BookBuilder {
+        BookBuilder::default()
+    }
+
+    /// The book title, read from the album tag.
+    ///
+    /// ```
+    /// let book = exporter::parse_book("../TestData/Huckfinn")?.unwrap();
+    /// assert_eq!(book.title(), "The Adventures of Huckleberry Finn");
+    /// assert!(book.author().contains("Mark Twain"));
+    /// # Ok::<(), color_eyre::Report>(())
+    /// ```
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The authors, read from the album artist tag.
+    pub fn author(&self) -> &BTreeSet<String> {
+        &self.author
+    }
+
+    /// The alphabetically first author, for places with room for only one.
+    pub fn primary_author(&self) -> Option<&str> {
+        self.author.first().map(String::as_str)
+    }
+
+    /// The authors in alphabetical order, separated by `sep`.
+    pub fn authors_joined(&self, sep: &str) -> String {
+        self.author
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(sep)
+    }
+
+    /// Whether one of the authors is `name`, ignoring case.
+    pub fn has_author(&self, name: &str, matching: NameMatch) -> bool {
+        let name = name.to_lowercase();
+        self.author.iter().any(|author| {
+            let author = author.to_lowercase();
+            match matching {
+                NameMatch::Exact => author == name,
+                NameMatch::Substring => author.contains(&name),
+            }
+        })
+    }
+
+    /// Whether an author reads the book, like for many memoirs. Case, spacing and
+    /// punctuation of the names don't matter, `H.G. Wells` reads `H. G. Wells`.
+    pub fn author_narrated(&self) -> bool {
+        let authors: HashSet<String> = self.author.iter().map(|name| name_key(name)).collect();
+        self.reader()
+            .iter()
+            .any(|reader| authors.contains(&name_key(reader)))
+    }
+
+    /// The readers of all tracks.
+    pub fn reader(&self) -> BTreeSet<String> {
+        self.tracks
+            .iter()
+            .flat_map(|track| track.reader.iter().cloned())
+            .collect()
+    }
+
+    /// Every track with its readers, for productions where the cast changes between tracks.
+    pub fn readers_by_track(&self) -> Vec<(&Track, &[String])> {
+        self.tracks
+            .iter()
+            .map(|track| (track, track.reader.as_slice()))
+            .collect()
+    }
+
+    /// The tracks, ordered by disc and track number.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// The number of files the book was read from.
+    pub fn total_tracks(&self) -> u32 {
+        self.total_tracks
+    }
+
+    /// The number of tracks according to the tags.
+    pub fn expected_tracks(&self) -> Option<u32> {
+        self.expected_tracks
+    }
+
+    /// The summed duration, `None` if it's unknown for any track.
+    pub fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    /// The total duration like `12h 34m`, or `45m` for less than an hour. Seconds are cut off.
+    pub fn total_duration_human(&self) -> Option<String> {
+        let minutes = self.total_duration?.as_secs() / 60;
+        Some(match (minutes / 60, minutes % 60) {
+            (0, minutes) => format!("{}m", minutes),
+            (hours, minutes) => format!("{}h {}m", hours, minutes),
+        })
+    }
+
+    pub fn discs(&self) -> Option<u32> {
+        self.discs
+    }
+
+    /// The disc numbers with their names, like `(1, "The Fellowship of the Ring")`.
+    pub fn disc_subtitles(&self) -> &[(u32, String)] {
+        &self.disc_subtitles
+    }
+
+    /// The ReplayGain adjustment of the whole book in dB.
+    pub fn replaygain_album(&self) -> Option<f32> {
+        self.replaygain_album
+    }
+
+    /// The rating of 1 to 5 stars, the best one of all tracks.
+    pub fn rating(&self) -> Option<u8> {
+        self.rating
+    }
+
+    /// Whether the tracks are meant to be played without gaps.
+    pub fn gapless(&self) -> Option<bool> {
+        self.gapless
+    }
+
+    /// Whether the recording is abridged, `None` if neither the tags nor the title tell.
+    pub fn abridged(&self) -> Option<bool> {
+        self.abridged
+    }
+
+    /// The year of the recording.
+    pub fn year(&self) -> Option<i32> {
+        self.year
+    }
+
+    /// The year the book was first published.
+    pub fn original_year(&self) -> Option<i32> {
+        self.original_year
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    /// The ISO 639-2 code of the language, like `eng`.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn publisher(&self) -> Option<&str> {
+        self.publisher.as_deref()
+    }
+
+    pub fn isbn(&self) -> Option<&str> {
+        self.isbn.as_deref()
+    }
+
+    pub fn asin(&self) -> Option<&str> {
+        self.asin.as_deref()
+    }
+
+    pub fn series(&self) -> Option<&str> {
+        self.series.as_deref()
+    }
+
+    /// The position in the series, fractional for novellas between two books.
+    pub fn series_index(&self) -> Option<f32> {
+        self.series_index
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    pub fn cover(&self) -> Option<&Cover> {
+        self.cover.as_ref()
+    }
+
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// Merges two parts of the same book.
+    ///
+    /// Title, number of discs, year, original year, genre, language, publisher, identifiers
+    /// and series have to match, everything else is combined.
+    pub fn merge(lhs: Self, rhs: Self) -> Result<Self, ParseError> {
+        Self::merge_with(lhs, rhs, MergeStrategy::Strict)
+    }
+
+    /// Like [`Book::merge`], conflicting discs, year and genre are resolved by `strategy`.
+    ///
+    /// Title, original year, language, publisher, identifiers and series always have to match.
+    pub fn merge_with(lhs: Self, rhs: Self, strategy: MergeStrategy) -> Result<Self, ParseError> {
+        // the conflicts are collected in field order, the first is the one to report
+        Self::try_merge_with(lhs, rhs, strategy).map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Like [`Book::merge`], but reports every conflicting field instead of only the first.
+    ///
+    /// The returned errors are never empty.
+    pub fn try_merge(lhs: Self, rhs: Self) -> Result<Self, Vec<ParseError>> {
+        Self::try_merge_with(lhs, rhs, MergeStrategy::Strict)
+    }
+
+    /// Like [`Book::merge_with`], but reports every conflicting field instead of only the first.
+    pub fn try_merge_with(
+        mut lhs: Self,
+        rhs: Self,
+        strategy: MergeStrategy,
+    ) -> Result<Self, Vec<ParseError>> {
+        let mut errors = Vec::new();
+        if lhs.title != rhs.title {
+            errors.push(ParseError::TitleMismatch {
+                left: lhs.title.clone(),
+                right: rhs.title,
+            });
+        }
+        let title = &lhs.title;
+        if let Some(discs) = collect_conflict(
+            resolve(
+                strategy,
+                (lhs.discs, mem::take(&mut lhs.votes.discs)),
+                (rhs.discs, rhs.votes.discs),
+                |left, right| ParseError::DiscsMismatch {
+                    title: title.clone(),
+                    left,
+                    right,
+                },
+            ),
+            &mut errors,
+        ) {
+            (lhs.discs, lhs.votes.discs) = discs;
+        }
+        if let Some(year) = collect_conflict(
+            resolve(
+                strategy,
+                (lhs.year, mem::take(&mut lhs.votes.year)),
+                (rhs.year, rhs.votes.year),
+                |left, right| ParseError::YearMismatch {
+                    title: title.clone(),
+                    left,
+                    right,
+                },
+            ),
+            &mut errors,
+        ) {
+            (lhs.year, lhs.votes.year) = year;
+        }
+        if let Some(genre) = collect_conflict(
+            resolve(
+                strategy,
+                (lhs.genre.take(), mem::take(&mut lhs.votes.genre)),
+                (rhs.genre, rhs.votes.genre),
+                |left, right| ParseError::GenreMismatch {
+                    title: title.clone(),
+                    left,
+                    right,
+                },
+            ),
+            &mut errors,
+        ) {
+            (lhs.genre, lhs.votes.genre) = genre;
+        }
+        if lhs.original_year != rhs.original_year {
+            errors.push(ParseError::OriginalYearMismatch {
+                title: lhs.title.clone(),
+                left: lhs.original_year,
+                right: rhs.original_year,
+            });
+        }
+        if lhs.language != rhs.language {
+            errors.push(ParseError::LanguageMismatch {
+                title: lhs.title.clone(),
+                left: lhs.language.clone(),
+                right: rhs.language,
+            });
+        }
+        if lhs.publisher != rhs.publisher {
+            errors.push(ParseError::PublisherMismatch {
+                title: lhs.title.clone(),
+                left: lhs.publisher.clone(),
+                right: rhs.publisher,
+            });
+        }
+        if lhs.isbn != rhs.isbn {
+            errors.push(ParseError::IsbnMismatch {
+                title: lhs.title.clone(),
+                left: lhs.isbn.clone(),
+                right: rhs.isbn,
+            });
+        }
+        if lhs.asin != rhs.asin {
+            errors.push(ParseError::AsinMismatch {
+                title: lhs.title.clone(),
+                left: lhs.asin.clone(),
+                right: rhs.asin,
+            });
+        }
+        if lhs.series != rhs.series || lhs.series_index != rhs.series_index {
+            errors.push(ParseError::SeriesMismatch {
+                title: lhs.title.clone(),
+                left: (lhs.series.clone(), lhs.series_index),
+                right: (rhs.series, rhs.series_index),
+            });
+        }
+        // the title only tells on some tracks, so only a contradiction is a conflict
+        match (lhs.abridged, rhs.abridged) {
+            (Some(left), Some(right)) if left != right => {
+                errors.push(ParseError::AbridgedMismatch {
+                    title: lhs.title.clone(),
+                    left,
+                    right,
+                });
+            }
+            (None, right) => lhs.abridged = right,
+            _ => {}
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // prefer the cover of the lowest numbered track
+        let first_track = |tracks: &[Track]| tracks.iter().map(Track::position).min();
+        if rhs.cover.is_some()
+            && (lhs.cover.is_none() || first_track(&rhs.tracks) < first_track(&lhs.tracks))
+        {
+            lhs.cover = rhs.cover;
+        }
+
+        // chapter marks are relative to their own file, they don't fit a multi file book
+        lhs.chapters.clear();
+        lhs.author.extend(rhs.author);
+        lhs.tracks.extend(rhs.tracks);
+        lhs.disc_subtitles.extend(rhs.disc_subtitles);
+        lhs.disc_subtitles.sort();
+        lhs.disc_subtitles.dedup();
+        lhs.total_tracks += rhs.total_tracks;
+        lhs.expected_tracks = lhs.expected_tracks.max(rhs.expected_tracks);
+        // the total is only known if every track knows its duration
+        lhs.total_duration = lhs
+            .total_duration
+            .zip(rhs.total_duration)
+            .map(|(lhs, rhs)| lhs + rhs);
+        // files of different releases can't share a gain, unlike the year that's no conflict
+        if lhs.replaygain_album != rhs.replaygain_album {
+            lhs.replaygain_album = None;
+        }
+        // one favorite chapter makes a favorite book
+        lhs.rating = lhs.rating.max(rhs.rating);
+        if lhs.gapless != rhs.gapless {
+            lhs.gapless = None;
+        }
+        // descriptions are often only on the first track or cut short on others
+        if rhs.description.as_ref().map(String::len) > lhs.description.as_ref().map(String::len) {
+            lhs.description = rhs.description;
+        }
+        for comment in rhs.comments {
+            if !lhs.comments.contains(&comment) {
+                lhs.comments.push(comment);
+            }
+        }
+
+        Ok(lhs)
+    }
+
+    /// Drops tracks read from the same file twice or from an identical copy, keeping the
+    /// totals in line.
+    pub(crate) fn remove_duplicate_tracks(&mut self) {
+        let mut seen = HashSet::new();
+        let before = self.tracks.len();
+        self.tracks.retain(|track| {
+            seen.insert((track.disc, track.track, track.title.clone(), track.duration))
+        });
+        self.recount_tracks();
+        if self.tracks.len() < before {
+            self.total_duration = self
+                .tracks
+                .iter()
+                .map(|track| track.duration)
+                .sum::<Option<Duration>>();
+        }
+    }
+
+    /// Sets the track count to the number of tracks, after they were added or removed by
+    /// hand.
+    pub fn recount_tracks(&mut self) {
+        self.total_tracks = self.tracks.len() as u32;
+    }
+
+    /// Strips the book title and a leading track number like `01 - ` from every track title,
+    /// `Huckleberry Finn - 01 - Chapter 1` becomes `Chapter 1`.
+    ///
+    /// A title that would end up empty, like `01`, is kept as it is.
+    pub fn clean_track_titles(&mut self) {
+        for track in &mut self.tracks {
+            let cleaned = clean_track_title(&track.title, &self.title);
+            if !cleaned.is_empty() {
+                track.title = cleaned.to_string();
+            }
+        }
+    }
+
+    /// The track numbers missing between 1 and the last track, a missing chapter usually
+    /// means a failed download.
+    ///
+    /// Each disc is checked up to its own last track, so the numbering may restart on every
+    /// disc. The track total of the tags only counts for books of a single disc.
+    pub fn missing_tracks(&self) -> Vec<u32> {
+        let mut discs: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+        for (disc, track) in self.tracks.iter().map(Track::position) {
+            discs.entry(disc).or_default().insert(track);
+        }
+        let single_disc = discs.len() <= 1;
+        if discs.is_empty() {
+            discs.insert(0, BTreeSet::new());
+        }
+
+        let mut missing = BTreeSet::new();
+        for present in discs.values() {
+            let mut last = present.last().copied().unwrap_or_default();
+            if single_disc {
+                last = last
+                    .max(self.total_tracks)
+                    .max(self.expected_tracks.unwrap_or_default());
+            }
+            missing.extend((1..=last).filter(|nr| !present.contains(nr)));
+        }
+        missing.into_iter().collect()
+    }
+
+    /// The lowest bitrate of all tracks, to find books worth downloading in better quality.
+    pub fn min_bitrate(&self) -> Option<u32> {
+        self.tracks
+            .iter()
+            .filter_map(|track| track.bitrate_kbps)
+            .min()
+    }
+
+    /// The disc numbers missing between 1 and the number of discs, empty if the tags don't
+    /// say how many discs there are.
+    pub fn missing_discs(&self) -> Vec<u32> {
+        let Some(discs) = self.discs else {
+            return Vec::new();
+        };
+        let present: BTreeSet<u32> = self.tracks.iter().filter_map(|track| track.disc).collect();
+        let last = present.last().copied().unwrap_or_default().max(discs);
+
+        (1..=last).filter(|nr| !present.contains(nr)).collect()
+    }
+
+    /// The number of different discs the tracks are on, a track without disc number counts
+    /// as on disc 1. Less than [`Book::discs`] for an incomplete set.
+    pub fn present_discs(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|track| track.disc.unwrap_or(1))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// The track numbers used by more than one file on the same disc, usually a ripping
+    /// mistake.
+    pub fn duplicate_tracks(&self) -> Vec<u32> {
+        let mut seen = HashSet::new();
+        let duplicates: BTreeSet<(u32, u32)> = self
+            .tracks
+            .iter()
+            .map(Track::position)
+            .filter(|position| !seen.insert(*position))
+            .collect();
+        duplicates.into_iter().map(|(_, track)| track).collect()
+    }
+
+    /// Whether as many files were found as the tags announce, an incomplete book is usually
+    /// a failed download.
+    ///
+    /// Books without a track total in their tags count as complete.
+    pub fn is_complete(&self) -> bool {
+        self.expected_tracks
+            .is_none_or(|expected| self.total_tracks >= expected)
+    }
+
+    /// The share of the announced tracks that were found, from 0 to 1, like 0.83 for a
+    /// download in progress.
+    ///
+    /// `None` if the tags don't announce a track total. Surplus tracks don't count, the
+    /// share never exceeds 1.
+    pub fn completeness(&self) -> Option<f32> {
+        let expected = self.expected_tracks.filter(|expected| *expected > 0)?;
+        Some((self.total_tracks as f32 / expected as f32).min(1.0))
+    }
+
+    /// A hash of title, authors, readers, year and tracks to detect changed metadata.
+    ///
+    /// The hash is FNV-1a over a fixed encoding, so it's the same on every machine and
+    /// doesn't depend on the order the files were found in.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = Fnv1a::default();
+        hash.write_str(&self.title);
+        for names in [&self.author, &self.reader()] {
+            hash.write(&(names.len() as u64).to_le_bytes());
+            for name in names {
+                hash.write_str(name);
+            }
+        }
+        hash.write(&self.year.map_or(i64::MIN, i64::from).to_le_bytes());
+
+        let mut tracks: Vec<((u32, u32), &str)> = self
+            .tracks
+            .iter()
+            .map(|track| (track.position(), track.title.as_str()))
+            .collect();
+        tracks.sort_unstable();
+        hash.write(&(tracks.len() as u64).to_le_bytes());
+        for ((disc, nr), title) in tracks {
+            hash.write(&disc.to_le_bytes());
+            hash.write(&nr.to_le_bytes());
+            hash.write_str(title);
+        }
+        hash.0
+    }
+
+    /// Writes the cover as `cover.jpg` or `cover.png` into `dir`, does nothing without a cover.
+    pub fn write_cover(&self, dir: &Path) -> Result<()> {
+        if let Some(cover) = &self.cover {
+            let path = dir.join(format!("cover.{}", cover.extension()));
+            std::fs::write(&path, &cover.data)
+                .wrap_err(format!("can't write cover: {:?}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// A one-line summary like `"Winnetou I" by Karl May (1893) — 5 tracks`, the year is left
+/// out if unknown.
+impl fmt::Display for Book {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.title)?;
+        if !self.author.is_empty() {
+            write!(f, " by {}", self.authors_joined(", "))?;
+        }
+        if let Some(year) = self.year {
+            write!(f, " ({})", year)?;
+        }
+        match self.total_tracks {
+            1 => write!(f, " — 1 track"),
+            tracks => write!(f, " — {} tracks", tracks),
+        }
+    }
+}
+
+/// Builds a [`Book`] without reading any file, see [`Book::builder`].
+///
+/// ```
+/// use exporter::{Book, Track};
+///
+/// let book = Book::builder()
+///     .title("The Adventures of Tom Sawyer")
+///     .author("Mark Twain")
+///     .track(Track::builder("Chapter 1", 1).reader("John Greenman").build())
+///     .build()?;
+/// assert_eq!(book.total_tracks(), 1);
+/// # Ok::<(), exporter::BuildError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct BookBuilder {
+    title: String,
+    author: BTreeSet<String>,
+    reader: BTreeSet<String>,
+    tracks: Vec<Track>,
+    total_tracks: Option<u32>,
+    expected_tracks: Option<u32>,
+    total_duration: Option<Duration>,
+    replaygain_album: Option<f32>,
+    rating: Option<u8>,
+    gapless: Option<bool>,
+    abridged: Option<bool>,
+    discs: Option<u32>,
+    disc_subtitles: Vec<(u32, String)>,
+    year: Option<i32>,
+    original_year: Option<i32>,
+    genre: Option<String>,
+    language: Option<String>,
+    publisher: Option<String>,
+    isbn: Option<String>,
+    asin: Option<String>,
+    series: Option<String>,
+    series_index: Option<f32>,
+    description: Option<String>,
+    comments: Vec<String>,
+    cover: Option<Cover>,
+    chapters: Vec<Chapter>,
+}
+
+impl BookBuilder {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Adds an author.
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author.insert(author.into());
+        self
+    }
+
+    /// Adds a reader of the book, it's added to the readers of every track on
+    /// [`build`](Self::build).
+    pub fn reader(mut self, reader: impl Into<String>) -> Self {
+        self.reader.insert(reader.into());
+        self
+    }
+
+    /// Adds a track, the tracks are ordered by disc and track number on
+    /// [`build`](Self::build).
+    pub fn track(mut self, track: Track) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    /// The number of files, has to match the number of tracks.
+    pub fn total_tracks(mut self, total_tracks: u32) -> Self {
+        self.total_tracks = Some(total_tracks);
+        self
+    }
+
+    pub fn expected_tracks(mut self, expected_tracks: u32) -> Self {
+        self.expected_tracks = Some(expected_tracks);
+        self
+    }
+
+    /// Overrides the duration summed up from the tracks.
+    pub fn total_duration(mut self, total_duration: Duration) -> Self {
+        self.total_duration = Some(total_duration);
+        self
+    }
+
+    pub fn discs(mut self, discs: u32) -> Self {
+        self.discs = Some(discs);
+        self
+    }
+
+    /// Names a disc of a box set.
+    pub fn disc_subtitle(mut self, disc: u32, subtitle: impl Into<String>) -> Self {
+        self.disc_subtitles.push((disc, subtitle.into()));
+        self
+    }
+
+    /// The ReplayGain adjustment of the whole book in dB.
+    pub fn replaygain_album(mut self, gain: f32) -> Self {
+        self.replaygain_album = Some(gain);
+        self
+    }
+
+    /// The rating of 1 to 5 stars, checked on [`build`](Self::build).
+    pub fn rating(mut self, rating: u8) -> Self {
+        self.rating = Some(rating);
+        self
+    }
+
+    /// Whether the tracks are meant to be played without gaps.
+    pub fn gapless(mut self, gapless: bool) -> Self {
+        self.gapless = Some(gapless);
+        self
+    }
+
+    /// Whether the recording is abridged.
+    pub fn abridged(mut self, abridged: bool) -> Self {
+        self.abridged = Some(abridged);
+        self
+    }
+
+    pub fn year(mut self, year: i32) -> Self {
+        self.year = Some(year);
+        self
+    }
+
+    pub fn original_year(mut self, original_year: i32) -> Self {
+        self.original_year = Some(original_year);
+        self
+    }
+
+    pub fn genre(mut self, genre: impl Into<String>) -> Self {
+        self.genre = Some(genre.into());
+        self
+    }
+
+    /// The ISO 639-2 code of the language, like `eng`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn publisher(mut self, publisher: impl Into<String>) -> Self {
+        self.publisher = Some(publisher.into());
+        self
+    }
+
+    /// The ISBN without hyphens, it isn't validated.
+    pub fn isbn(mut self, isbn: impl Into<String>) -> Self {
+        self.isbn = Some(isbn.into());
+        self
+    }
+
+    pub fn asin(mut self, asin: impl Into<String>) -> Self {
+        self.asin = Some(asin.into());
+        self
+    }
+
+    pub fn series(mut self, series: impl Into<String>, index: Option<f32>) -> Self {
+        self.series = Some(series.into());
+        self.series_index = index;
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a comment.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comments.push(comment.into());
+        self
+    }
+
+    pub fn cover(mut self, cover: Cover) -> Self {
+        self.cover = Some(cover);
+        self
+    }
+
+    /// Adds a chapter mark.
+    pub fn chapter(mut self, chapter: Chapter) -> Self {
+        self.chapters.push(chapter);
+        self
+    }
+
+    /// Checks the title isn't empty, the track count matches the tracks and the rating is
+    /// 1 to 5 stars.
+    pub fn build(self) -> Result<Book, BuildError> {
+        let mut parts = self;
+        if parts.title.trim().is_empty() {
+            return Err(BuildError::EmptyTitle);
+        }
+        let tracks = parts.tracks.len() as u32;
+        let total_tracks = parts.total_tracks.unwrap_or(tracks);
+        if total_tracks != tracks {
+            return Err(BuildError::TrackCountMismatch {
+                total_tracks,
+                tracks,
+            });
+        }
+        if let Some(rating) = parts.rating.filter(|rating| !(1..=5).contains(rating)) {
+            return Err(BuildError::InvalidRating(rating));
+        }
+
+        parts.tracks.sort_by_key(Track::position);
+        parts.disc_subtitles.sort();
+        parts.disc_subtitles.dedup();
+        for track in &mut parts.tracks {
+            for reader in &parts.reader {
+                if !track.reader.contains(reader) {
+                    track.reader.push(reader.clone());
+                }
+            }
+        }
+        let total_duration = parts.total_duration.or_else(|| {
+            parts
+                .tracks
+                .iter()
+                .map(|track| track.duration)
+                .sum::<Option<Duration>>()
+        });
+
+        Ok(Book {
+            title: parts.title,
+            author: parts.author,
+            tracks: parts.tracks,
+            total_tracks,
+            expected_tracks: parts.expected_tracks,
+            total_duration,
+            replaygain_album: parts.replaygain_album,
+            rating: parts.rating,
+            gapless: parts.gapless,
+            abridged: parts.abridged,
+            discs: parts.discs,
+            disc_subtitles: parts.disc_subtitles,
+            year: parts.year,
+            original_year: parts.original_year,
+            genre: parts.genre,
+            language: parts.language,
+            publisher: parts.publisher,
+            isbn: parts.isbn,
+            asin: parts.asin,
+            series: parts.series,
+            series_index: parts.series_index,
+            description: parts.description,
+            comments: parts.comments,
+            cover: parts.cover,
+            chapters: parts.chapters,
+            votes: Votes::default(),
+        })
+    }
+}
+
+/// Builds a [`Track`], see [`Track::builder`].
+#[derive(Debug)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl TrackBuilder {
+    /// Adds a reader.
+    pub fn reader(mut self, reader: impl Into<String>) -> Self {
+        self.track.reader.push(reader.into());
+        self
+    }
+
+    pub fn disc(mut self, disc: u32) -> Self {
+        self.track.disc = Some(disc);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.track.duration = Some(duration);
+        self
+    }
+
+    /// The average bitrate of the audio stream.
+    pub fn bitrate_kbps(mut self, bitrate: u32) -> Self {
+        self.track.bitrate_kbps = Some(bitrate);
+        self
+    }
+
+    pub fn sample_rate_hz(mut self, sample_rate: u32) -> Self {
+        self.track.sample_rate_hz = Some(sample_rate);
+        self
+    }
+
+    /// The ReplayGain adjustment in dB.
+    pub fn replaygain(mut self, gain: f32) -> Self {
+        self.track.replaygain_track = Some(gain);
+        self
+    }
+
+    pub fn transcript(mut self, transcript: impl Into<String>) -> Self {
+        self.track.transcript = Some(transcript.into());
+        self
+    }
+
+    pub fn encoder(mut self, encoder: impl Into<String>) -> Self {
+        self.track.encoder = Some(encoder.into());
+        self
+    }
+
+    /// The work of an omnibus edition and its number.
+    pub fn movement(mut self, movement: impl Into<String>, index: u32) -> Self {
+        self.track.movement = Some(movement.into());
+        self.track.movement_index = Some(index);
+        self
+    }
+
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.track.path = path.into();
+        self
+    }
+
+    pub fn build(self) -> Track {
+        self.track
+    }
+}
+
+/// The 64 bit FNV-1a hash, unlike `DefaultHasher` its output is guaranteed to be stable.
+pub(crate) struct Fnv1a(pub(crate) u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Writes the length first, so `("ab", "c")` and `("a", "bc")` differ.
+    pub(crate) fn write_str(&mut self, value: &str) {
+        self.write(&(value.len() as u64).to_le_bytes());
+        self.write(value.as_bytes());
+    }
+}
+
+/// Serializes a duration as fractional seconds.
+pub(crate) fn serialize_seconds<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_secs_f64()).serialize(serializer)
+}
+
+/// Like [`serialize_seconds`], for a duration that is always known.
+fn serialize_duration<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration.as_secs_f64().serialize(serializer)
+}
+
+/// Reads a duration written by [`serialize_seconds`].
+fn deserialize_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let seconds: Option<f64> = Option::deserialize(deserializer)?;
+    seconds
+        .map(|seconds| Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Reads a duration written by [`serialize_duration`].
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    Duration::try_from_secs_f64(f64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// Serializes a path with forward slashes on every platform, for use with
+/// `#[serde(serialize_with = "exporter::book::serialize_path")]`.
+///
+/// Keeps the output of a library comparable between Windows and Unix machines. A backslash
+/// is only a separator on Windows or in a Windows path like `C:\Audiobooks`, elsewhere it's
+/// part of a file name and kept.
+pub fn serialize_path<P: AsRef<Path>, S: Serializer>(
+    path: P,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let path = path.as_ref().to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '\\' || is_windows_path(&path) {
+        path.replace('\\', "/").serialize(serializer)
+    } else {
+        path.serialize(serializer)
+    }
+}
+
+/// Whether `path` starts with a drive like `C:\` or a share like `\\nas`.
+fn is_windows_path(path: &str) -> bool {
+    match path.as_bytes() {
+        [drive, b':', b'\\', ..] => drive.is_ascii_alphabetic(),
+        [b'\\', b'\\', ..] => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn track(nr: u32) -> Track {
+        Track::builder(format!("Chapter {}", nr), nr)
+            .reader("John Greenman")
+            .duration(Duration::from_secs(60))
+            .build()
+    }
+
+    #[test]
+    fn test_builder() {
+        let book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .author("Mark Twain")
+            .track(track(2))
+            .track(track(1))
+            .year(1876)
+            .build()
+            .unwrap();
+
+        assert_eq!(book.total_tracks(), 2);
+        assert_eq!(book.tracks()[0].track(), 1);
+        assert_eq!(book.total_duration(), Some(Duration::from_secs(120)));
+        assert!(book.reader().contains("John Greenman"));
+        assert_eq!(book.year(), Some(1876));
+        assert!(book.is_complete());
+    }
+
+    #[test]
+    fn test_builder_invariants() {
+        let err = Book::builder().title("  ").build().unwrap_err();
+        assert!(matches!(err, BuildError::EmptyTitle));
+
+        let err = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .track(track(1))
+            .total_tracks(2)
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BuildError::TrackCountMismatch {
+                total_tracks: 2,
+                tracks: 1
+            }
+        ));
+
+        for rating in [0, 6, 9] {
+            let err = Book::builder()
+                .title("The Adventures of Tom Sawyer")
+                .rating(rating)
+                .build()
+                .unwrap_err();
+            assert!(matches!(err, BuildError::InvalidRating(r) if r == rating));
+        }
+        let book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .rating(5)
+            .build()
+            .unwrap();
+        assert_eq!(book.rating(), Some(5));
+    }
+
+    #[test]
+    fn test_authors() {
+        let book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .author("Mark Twain")
+            .build()
+            .unwrap();
+        assert_eq!(book.primary_author(), Some("Mark Twain"));
+        assert_eq!(book.authors_joined(", "), "Mark Twain");
+
+        let book = Book::builder()
+            .title("The Gilded Age")
+            .author("Mark Twain")
+            .author("Charles Dudley Warner")
+            .build()
+            .unwrap();
+        assert_eq!(book.primary_author(), Some("Charles Dudley Warner"));
+        assert_eq!(
+            book.authors_joined(" & "),
+            "Charles Dudley Warner & Mark Twain"
+        );
+
+        let book = Book::builder().title("Beowulf").build().unwrap();
+        assert_eq!(book.primary_author(), None);
+        assert_eq!(book.authors_joined(", "), "");
+    }
+
+    #[test]
+    fn test_clean_track_titles() {
+        let mut book = Book::builder()
+            .title("Huckleberry Finn")
+            .track(Track::builder("Huckleberry Finn - 01 - Chapter 1", 1).build())
+            .track(Track::builder("02 - Chapter 2", 2).build())
+            .track(Track::builder("huckleberry finn: Chapter 3", 3).build())
+            .track(Track::builder("04", 4).build())
+            .track(Track::builder("Chapter 5", 5).build())
+            .track(Track::builder("1885 Preface", 6).build())
+            .track(Track::builder("Huckleberry Finnish", 7).build())
+            .build()
+            .unwrap();
+        book.clean_track_titles();
+        let titles: Vec<_> = book
+            .tracks
+            .iter()
+            .map(|track| track.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Chapter 1",
+                "Chapter 2",
+                "Chapter 3",
+                "04",
+                "Chapter 5",
+                "1885 Preface",
+                "Huckleberry Finnish"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_has_author() {
+        let book = Book::builder()
+            .title("The Gilded Age")
+            .author("Mark Twain")
+            .author("Charles Dudley Warner")
+            .build()
+            .unwrap();
+        assert!(book.has_author("mark twain", NameMatch::Exact));
+        assert!(!book.has_author("Twain", NameMatch::Exact));
+        assert!(book.has_author("twain", NameMatch::Substring));
+        assert!(book.has_author("DUDLEY", NameMatch::Substring));
+        assert!(!book.has_author("Dickens", NameMatch::Substring));
+    }
+
+    #[test]
+    fn test_total_duration_human() {
+        let book = |duration| {
+            Book::builder()
+                .title("The Adventures of Tom Sawyer")
+                .total_duration(duration)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            book(Duration::from_secs(45 * 60 + 59)).total_duration_human(),
+            Some("45m".to_string())
+        );
+        assert_eq!(
+            book(Duration::from_secs(12 * 3600 + 34 * 60)).total_duration_human(),
+            Some("12h 34m".to_string())
+        );
+        assert_eq!(
+            book(Duration::from_secs(12 * 3600)).total_duration_human(),
+            Some("12h 0m".to_string())
+        );
+
+        let book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .track(Track::builder("Chapter 1", 1).build())
+            .build()
+            .unwrap();
+        assert_eq!(book.total_duration_human(), None);
+    }
+
+    #[test]
+    fn test_readers_by_track() {
+        let book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .track(
+                Track::builder("Chapter 1", 1)
+                    .reader("John Greenman")
+                    .build(),
+            )
+            .track(
+                Track::builder("Chapter 2", 2)
+                    .reader("Mark F. Smith")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let readers: Vec<(u32, &[String])> = book
+            .readers_by_track()
+            .into_iter()
+            .map(|(track, readers)| (track.track, readers))
+            .collect();
+        assert_eq!(
+            readers,
+            vec![
+                (1, &["John Greenman".to_string()][..]),
+                (2, &["Mark F. Smith".to_string()][..]),
+            ]
+        );
+        assert_eq!(book.reader().len(), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        let book = crate::parse_book("../TestData/Winnetou").unwrap().unwrap();
+        assert_eq!(book.to_string(), "\"Winnetou I\" by Karl May — 5 tracks");
+
+        let book = Book::builder()
+            .title("Good Omens")
+            .author("Terry Pratchett")
+            .author("Neil Gaiman")
+            .year(1990)
+            .track(track(1))
+            .build()
+            .unwrap();
+        assert_eq!(
+            book.to_string(),
+            "\"Good Omens\" by Neil Gaiman, Terry Pratchett (1990) — 1 track"
+        );
+    }
+
+    #[test]
+    fn test_author_narrated() {
+        let book = |author: &str, reader: &str| {
+            Book::builder()
+                .title("Narrative of the Life of Frederick Douglass")
+                .author(author)
+                .track(Track::builder("Chapter 1", 1).reader(reader).build())
+                .build()
+                .unwrap()
+        };
+        assert!(book("Frederick Douglass", "frederick  douglass").author_narrated());
+        assert!(book("H. G. Wells", "H.G. Wells").author_narrated());
+        assert!(!book("Frederick Douglass", "Mark Smith").author_narrated());
+        assert!(!Book::builder()
+            .title("Anonymous")
+            .track(track(1))
+            .build()
+            .unwrap()
+            .author_narrated());
+    }
+
+    #[test]
+    fn test_completeness() {
+        let book = |tracks: u32, expected: Option<u32>| {
+            let mut builder = Book::builder().title("Twelve Years a Slave");
+            for nr in 1..=tracks {
+                builder = builder.track(Track::builder(format!("Chapter {}", nr), nr).build());
+            }
+            if let Some(expected) = expected {
+                builder = builder.expected_tracks(expected);
+            }
+            builder.build().unwrap()
+        };
+        assert_eq!(book(12, Some(12)).completeness(), Some(1.0));
+        let partial = book(11, Some(12)).completeness().unwrap();
+        assert!((partial - 0.917).abs() < 0.001, "{}", partial);
+        assert_eq!(book(13, Some(12)).completeness(), Some(1.0));
+        assert_eq!(book(11, None).completeness(), None);
+    }
+
+    #[test]
+    fn test_recount_tracks() {
+        let mut book = Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .track(Track::builder("Chapter 1", 1).build())
+            .track(Track::builder("Chapter 2", 2).build())
+            .build()
+            .unwrap();
+        book.tracks.push(Track::builder("Chapter 3", 3).build());
+        assert_eq!(book.total_tracks, 2);
+        book.recount_tracks();
+        assert_eq!(book.total_tracks, 3);
+    }
+
+    #[test]
+    fn test_merge_replaygain() {
+        let book = |nr, gain| {
+            Book::builder()
+                .title("The Jungle Book")
+                .replaygain_album(gain)
+                .track(
+                    Track::builder(format!("Part {}", nr), nr)
+                        .replaygain(-5.9)
+                        .build(),
+                )
+                .build()
+                .unwrap()
+        };
+        let merged = Book::merge(book(1, -6.48), book(2, -6.48)).unwrap();
+        assert_eq!(merged.replaygain_album, Some(-6.48));
+        assert_eq!(merged.tracks[1].replaygain_track, Some(-5.9));
+
+        let merged = Book::merge(merged, book(3, -3.0)).unwrap();
+        assert_eq!(merged.replaygain_album, None);
+    }
+
+    #[test]
+    fn test_try_merge() {
+        let book = |title: &str, year| {
+            Book::builder()
+                .title(title)
+                .author("Mark Twain")
+                .year(year)
+                .track(track(1))
+                .build()
+                .unwrap()
+        };
+
+        let errors = Book::try_merge(
+            book("The Adventures of Tom Sawyer", 1876),
+            book("Tom Sawyer Abroad", 1894),
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], ParseError::TitleMismatch { .. }));
+        assert!(matches!(
+            errors[1],
+            ParseError::YearMismatch {
+                left: Some(1876),
+                right: Some(1894),
+                ..
+            }
+        ));
+
+        // merge reports the first of them
+        let err = Book::merge(
+            book("The Adventures of Tom Sawyer", 1876),
+            book("Tom Sawyer Abroad", 1894),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ParseError::TitleMismatch { .. }));
+
+        let merged = Book::try_merge(
+            book("The Adventures of Tom Sawyer", 1876),
+            book("The Adventures of Tom Sawyer", 1876),
+        )
+        .unwrap();
+        assert_eq!(merged.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_serialize_path() {
+        #[derive(Serialize)]
+        struct File {
+            #[serde(serialize_with = "serialize_path")]
+            path: PathBuf,
+        }
+
+        let file = File {
+            path: PathBuf::from(r"C:\Audiobooks\Mark Twain\Huckfinn\01.mp3"),
+        };
+        assert_eq!(
+            serde_json::to_string(&file).unwrap(),
+            r#"{"path":"C:/Audiobooks/Mark Twain/Huckfinn/01.mp3"}"#
+        );
+
+        // on Unix a backslash is part of the file name
+        let file = File {
+            path: PathBuf::from(r"/audiobooks/AC\DC/01.mp3"),
+        };
+        let expected = if cfg!(windows) {
+            r#"{"path":"/audiobooks/AC/DC/01.mp3"}"#
+        } else {
+            r#"{"path":"/audiobooks/AC\\DC/01.mp3"}"#
+        };
+        assert_eq!(serde_json::to_string(&file).unwrap(), expected);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::{parse_book, Book};
+
+/// Books parsed before, so a rescan only reads directories that changed.
+///
+/// A directory is read again as soon as any file in it was added, removed or has a
+/// different modification time or size. Saved as JSON with [`Cache::save`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: HashMap<PathBuf, CacheEntry>,
+    #[serde(skip)]
+    hits: u32,
+    #[serde(skip)]
+    misses: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    files: Vec<FileStamp>,
+    book: Option<Book>,
+    /// The paths of the tracks in order, they aren't part of the serialized book.
+    track_paths: Vec<PathBuf>,
+}
+
+/// What identifies the version of a file without reading it.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FileStamp {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+impl Cache {
+    /// Reads a cache written by [`Cache::save`], a missing file is an empty cache.
+    pub fn load(path: &Path) -> Result<Cache> {
+        match std::fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .wrap_err(format!("can't read cache: {:?}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Cache::default()),
+            Err(e) => Err(e).wrap_err(format!("can't read cache: {:?}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(self).wrap_err("can't serialize cache")?;
+        std::fs::write(path, json).wrap_err(format!("can't write cache: {:?}", path.display()))
+    }
+
+    /// How many books were taken from the cache since it was loaded.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// How many books had to be parsed since the cache was loaded.
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+}
+
+/// Like [`parse_book`], but returns the book from `cache` if no file in the directory changed.
+///
+/// Cover images aren't cached to keep the file small, a cached book knows the type of its
+/// cover but has no image data.
+pub fn parse_book_cached<P: AsRef<Path>>(path: P, cache: &mut Cache) -> Result<Option<Book>> {
+    let path = path.as_ref();
+    let files = file_stamps(path)?;
+    if let Some(entry) = cache.entries.get(path) {
+        if entry.files == files {
+            cache.hits += 1;
+            let mut book = entry.book.clone();
+            if let Some(book) = &mut book {
+                for (track, path) in book.tracks.iter_mut().zip(&entry.track_paths) {
+                    track.path = path.clone();
+                }
+            }
+            return Ok(book);
+        }
+    }
+
+    cache.misses += 1;
+    let book = parse_book(path)?;
+    let track_paths = book
+        .iter()
+        .flat_map(|book| book.tracks.iter().map(|track| track.path.clone()))
+        .collect();
+    cache.entries.insert(
+        path.to_path_buf(),
+        CacheEntry {
+            files,
+            book: book.clone(),
+            track_paths,
+        },
+    );
+    Ok(book)
+}
+
+/// The stamps of all files in `dir`, ordered by path.
+fn file_stamps(dir: &Path) -> Result<Vec<FileStamp>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_file() {
+            files.push(FileStamp {
+                path: entry.path(),
+                modified: meta.modified()?,
+                size: meta.len(),
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_book_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let book_dir = dir.path().join("Huckfinn");
+        std::fs::create_dir(&book_dir).unwrap();
+        for entry in std::fs::read_dir("../TestData/Huckfinn").unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), book_dir.join(entry.file_name())).unwrap();
+        }
+        let cache_file = dir.path().join("cache.json");
+
+        let mut cache = Cache::load(&cache_file).unwrap();
+        let book = parse_book_cached(&book_dir, &mut cache).unwrap().unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        cache.save(&cache_file).unwrap();
+
+        // a new scan of the unchanged directory doesn't read the tags
+        let mut cache = Cache::load(&cache_file).unwrap();
+        let cached = parse_book_cached(&book_dir, &mut cache).unwrap().unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 0));
+        assert_eq!(cached.title, book.title);
+        assert_eq!(cached.tracks.len(), book.tracks.len());
+        assert_eq!(cached.tracks[0].path, book.tracks[0].path);
+        assert_eq!(cached.content_hash(), book.content_hash());
+
+        // touching a single file invalidates the book
+        let track = std::fs::File::options()
+            .write(true)
+            .open(book_dir.join("huckfinn_01_twain_apc_64kb.mp3"))
+            .unwrap();
+        track
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        parse_book_cached(&book_dir, &mut cache).unwrap().unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{path::Path, time::Duration};
+
+use tracing::warn;
+
+use crate::{Book, Chapter, ParseError};
+
+/// The file name of the chapter list next to a single-file book.
+const CHAPTERS_FILE: &str = "chapters.txt";
+
+/// A chapter before its end is known.
+#[derive(Debug, PartialEq)]
+struct ChapterStart {
+    title: String,
+    start: Duration,
+    /// Only ffmetadata knows the end, otherwise it's the start of the next chapter.
+    end: Option<Duration>,
+}
+
+/// Replaces the chapters of a book made of a single file by the `chapters.txt` in `dir`.
+///
+/// The file is either in ffmpeg's metadata format, starting with `;FFMETADATA1`, or has a
+/// line like `01:02:03 Title` per chapter. Does nothing if the book has more than one track
+/// or there's no such file.
+pub(crate) fn apply_chapters_file(book: &mut Book, dir: &Path) -> Result<(), ParseError> {
+    if book.tracks.len() != 1 {
+        return Ok(());
+    }
+    let path = dir.join(CHAPTERS_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ParseError::unreadable(path, e)),
+    };
+
+    let starts = if text.trim_start().starts_with(";FFMETADATA1") {
+        parse_ffmetadata(&text)
+    } else {
+        parse_timestamps(&text)
+    };
+    if starts.is_empty() {
+        warn!("No chapters in {:?}", path.display());
+        return Ok(());
+    }
+    if !book.chapters.is_empty() {
+        warn!(
+            "Replacing the embedded chapters of {:?} by {:?}",
+            book.title,
+            path.display()
+        );
+    }
+
+    // without a duration the last chapter is empty
+    let duration = book.tracks[0].duration;
+    let ends: Vec<Duration> = starts
+        .iter()
+        .skip(1)
+        .map(|next| next.start)
+        .chain([duration.unwrap_or_else(|| starts[starts.len() - 1].start)])
+        .collect();
+    book.chapters = starts
+        .into_iter()
+        .zip(ends)
+        .map(|(chapter, next)| Chapter {
+            title: chapter.title,
+            start: chapter.start,
+            end: chapter.end.unwrap_or(next),
+        })
+        .collect();
+    Ok(())
+}
+
+/// The `[CHAPTER]` sections of an ffmetadata file, sections without `START` are left out.
+fn parse_ffmetadata(text: &str) -> Vec<ChapterStart> {
+    // the timestamps are only known once the TIMEBASE line is read, it may come last
+    struct Section {
+        timebase: (u64, u64),
+        start: Option<u64>,
+        end: Option<u64>,
+        title: Option<String>,
+    }
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_chapter = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with(';') || line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_chapter = line.eq_ignore_ascii_case("[CHAPTER]");
+            if in_chapter {
+                sections.push(Section {
+                    timebase: (1, 1_000_000_000),
+                    start: None,
+                    end: None,
+                    title: None,
+                });
+            }
+            continue;
+        }
+        let (Some(section), Some((key, value))) = (
+            sections.last_mut().filter(|_| in_chapter),
+            split_key_value(line),
+        ) else {
+            continue;
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "TIMEBASE" => {
+                if let Some(timebase) = value
+                    .split_once('/')
+                    .and_then(|(num, den)| Some((num.parse().ok()?, den.parse().ok()?)))
+                    .filter(|(_, den)| *den > 0)
+                {
+                    section.timebase = timebase;
+                }
+            }
+            "START" => section.start = value.parse().ok(),
+            "END" => section.end = value.parse().ok(),
+            "TITLE" => section.title = Some(value),
+            _ => {}
+        }
+    }
+
+    sections
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, section)| {
+            let (num, den) = section.timebase;
+            let at = |ticks: u64| {
+                let nanos = u128::from(ticks) * u128::from(num) * 1_000_000_000 / u128::from(den);
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            };
+            Some(ChapterStart {
+                title: section
+                    .title
+                    .unwrap_or_else(|| format!("Chapter {}", idx + 1)),
+                start: at(section.start?),
+                end: section.end.map(at),
+            })
+        })
+        .collect()
+}
+
+/// Splits `key=value` at the first `=` and drops the backslashes escaping `=;#\` and
+/// newlines in the value.
+fn split_key_value(line: &str) -> Option<(&str, String)> {
+    let (key, value) = line.split_once('=')?;
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Some((key.trim(), unescaped))
+}
+
+/// The lines of a chapter list like `01:02:03 Title` or `2:03.5 Title`, lines not starting
+/// with a timestamp are left out.
+fn parse_timestamps(text: &str) -> Vec<ChapterStart> {
+    text.lines()
+        .filter_map(|line| {
+            let (timestamp, title) = line.trim().split_once(char::is_whitespace)?;
+            Some(ChapterStart {
+                title: title.trim().to_string(),
+                start: parse_timestamp(timestamp)?,
+                end: None,
+            })
+        })
+        .collect()
+}
+
+/// Parses `hh:mm:ss`, `mm:ss` and either with fractional seconds.
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let mut parts = timestamp.rsplit(':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let hours: u64 = match parts.next() {
+        Some(hours) => hours.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || !(0.0..60.0).contains(&seconds) || minutes >= 60 {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::parse_book;
+
+    #[test]
+    fn test_chapters_file() {
+        let book = parse_book("../TestData/Frankenstein").unwrap().unwrap();
+        insta::assert_yaml_snapshot!(book.chapters);
+
+        let book = parse_book("../TestData/The Hound of the Baskervilles")
+            .unwrap()
+            .unwrap();
+        insta::assert_yaml_snapshot!(book.chapters);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(
+            parse_timestamp("1:02:03.5"),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(parse_timestamp("2:03"), Some(Duration::from_secs(123)));
+        assert_eq!(parse_timestamp("00:60:00"), None);
+        assert_eq!(parse_timestamp("123"), None);
+        assert_eq!(parse_timestamp("Chapter"), None);
+    }
+
+    #[test]
+    fn test_parse_ffmetadata() {
+        let chapters = parse_ffmetadata(
+            ";FFMETADATA1\ntitle=Book\n[CHAPTER]\nSTART=90\nEND=180\nTIMEBASE=1/45\ntitle=A \\= B\n[STREAM]\ntitle=Not a chapter\n",
+        );
+        assert_eq!(
+            chapters,
+            vec![ChapterStart {
+                title: "A = B".to_string(),
+                start: Duration::from_secs(2),
+                end: Some(Duration::from_secs(4)),
+            }]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::{fmt::Write, path::PathBuf, time::Duration};
+
+use tracing::warn;
+
+use crate::{Book, Chapter};
+
+/// What it takes to join the files of a book into a single file with ffmpeg, see
+/// [`Book::concat_plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcatPlan {
+    pub title: String,
+    /// The authors, separated by `, `.
+    pub author: String,
+    /// The files in playing order, each only once.
+    pub files: Vec<PathBuf>,
+    /// One chapter per track, relative to the start of the joined file.
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    /// Plans joining the files of the book, like into one M4B, without encoding anything.
+    ///
+    /// Every track becomes a chapter titled like the track. A track of unknown duration
+    /// gets an empty chapter, so the following ones start too early.
+    pub fn concat_plan(&self) -> ConcatPlan {
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut chapters = Vec::with_capacity(self.tracks.len());
+        let mut start = Duration::ZERO;
+        for track in &self.tracks {
+            // tracks of a cue sheet share their file
+            if files.last() != Some(&track.path) {
+                files.push(track.path.clone());
+            }
+            let duration = track.duration.unwrap_or_else(|| {
+                warn!(
+                    "Track {:?} of {:?} has no duration, its chapter is empty",
+                    track.title, self.title
+                );
+                Duration::ZERO
+            });
+            chapters.push(Chapter::new(&track.title, start, start + duration));
+            start += duration;
+        }
+
+        ConcatPlan {
+            title: self.title.clone(),
+            author: self.authors_joined(", "),
+            files,
+            chapters,
+        }
+    }
+}
+
+impl ConcatPlan {
+    /// The input of ffmpeg's concat demuxer, `ffmpeg -f concat -safe 0 -i files.txt`.
+    pub fn concat_list(&self) -> String {
+        let mut list = String::new();
+        for file in &self.files {
+            // the demuxer only knows single quotes, a quote is closed, escaped and reopened
+            let file = file.to_string_lossy().replace('\'', r"'\''");
+            writeln!(list, "file '{}'", file).expect("writing to a String can't fail");
+        }
+        list
+    }
+
+    /// The title, author and chapters in ffmpeg's metadata format, used with
+    /// `-i metadata.txt -map_metadata 1`.
+    pub fn ffmetadata(&self) -> String {
+        let mut metadata = String::from(";FFMETADATA1\n");
+        let mut line = |key: &str, value: &str| {
+            writeln!(metadata, "{}={}", key, escape(value))
+                .expect("writing to a String can't fail");
+        };
+        line("title", &self.title);
+        line("album", &self.title);
+        line("artist", &self.author);
+        for chapter in &self.chapters {
+            metadata.push_str("\n[CHAPTER]\nTIMEBASE=1/1000\n");
+            writeln!(
+                metadata,
+                "START={}\nEND={}\ntitle={}",
+                chapter.start().as_millis(),
+                chapter.end().as_millis(),
+                escape(chapter.title())
+            )
+            .expect("writing to a String can't fail");
+        }
+        metadata
+    }
+}
+
+/// Escapes the characters with a meaning in ffmetadata by a backslash.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::parse_book;
+
+    #[test]
+    fn test_ffmetadata() {
+        let book = parse_book("../TestData/Winnetou").unwrap().unwrap();
+        let plan = book.concat_plan();
+        assert_eq!(plan.files.len(), book.tracks().len());
+        assert_eq!(plan.chapters.len(), book.tracks().len());
+        insta::assert_snapshot!(plan.ffmetadata());
+    }
+
+    #[test]
+    fn test_concat_list() {
+        let plan = ConcatPlan {
+            title: "Winnetou I".to_string(),
+            author: "Karl May".to_string(),
+            files: vec![
+                Path::new("/books/Winnetou/01.mp3").to_path_buf(),
+                Path::new("/books/Winnetou/Old Shatterhand's Test.mp3").to_path_buf(),
+            ],
+            chapters: Vec::new(),
+        };
+        assert_eq!(
+            plan.concat_list(),
+            "file '/books/Winnetou/01.mp3'\nfile '/books/Winnetou/Old Shatterhand'\\''s Test.mp3'\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Act 1; Scene = 2 #3"), r"Act 1\; Scene \= 2 \#3");
+    }
+}
@@ -0,0 +1,211 @@
+use std::{ffi::OsStr, path::Path, time::Duration};
+
+use tracing::warn;
+
+use crate::{Book, ParseError, Track};
+
+/// A `TRACK` of a cue sheet.
+#[derive(Debug, PartialEq)]
+struct CueTrack {
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    /// The position of `INDEX 01` in the file.
+    start: Duration,
+}
+
+/// Splits a book made of a single file into the tracks of the cue sheet next to it.
+///
+/// Does nothing if the book has more than one track, there's no `.cue` file in `dir` or the
+/// cue sheet belongs to another file.
+pub(crate) fn split_by_cue(book: &mut Book, dir: &Path) -> Result<(), ParseError> {
+    if book.tracks.len() != 1 {
+        return Ok(());
+    }
+    let mut cue_files: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| ParseError::unreadable(dir, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("cue"))
+        })
+        .collect();
+    cue_files.sort();
+    let Some(cue_file) = cue_files.first() else {
+        return Ok(());
+    };
+    let sheet =
+        std::fs::read_to_string(cue_file).map_err(|e| ParseError::unreadable(cue_file, e))?;
+
+    let file = &book.tracks[0];
+    let file_name = file.path.file_name().and_then(OsStr::to_str).unwrap_or("");
+    let (files, cue_tracks) = parse_cue(&sheet);
+    if !files.is_empty() && !files.iter().any(|name| name == file_name) {
+        warn!(
+            "Ignoring {:?}, it doesn't belong to {:?}",
+            cue_file.display(),
+            file_name
+        );
+        return Ok(());
+    }
+    if cue_tracks.is_empty() {
+        return Ok(());
+    }
+
+    let ends = cue_tracks
+        .iter()
+        .skip(1)
+        .map(|next| Some(next.start))
+        .chain([file.duration]);
+    let tracks: Vec<Track> = cue_tracks
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(idx, (cue_track, end))| Track {
+            title: cue_track
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("Track {}", cue_track.number)),
+            reader: match &cue_track.performer {
+                Some(performer) => vec![performer.clone()],
+                None => file.reader.clone(),
+            },
+            track: cue_track.number,
+            disc: file.disc,
+            duration: end.and_then(|end| end.checked_sub(cue_track.start)),
+            bitrate_kbps: file.bitrate_kbps,
+            sample_rate_hz: file.sample_rate_hz,
+            replaygain_track: file.replaygain_track,
+            // the transcript of the whole file, it's kept once
+            transcript: file.transcript.clone().filter(|_| idx == 0),
+            encoder: file.encoder.clone(),
+            movement: file.movement.clone(),
+            movement_index: file.movement_index,
+            path: file.path.clone(),
+        })
+        .collect();
+
+    book.tracks = tracks;
+    book.recount_tracks();
+    Ok(())
+}
+
+/// The file names and tracks of a cue sheet, tracks without `INDEX 01` are left out.
+fn parse_cue(sheet: &str) -> (Vec<String>, Vec<CueTrack>) {
+    let mut files = Vec::new();
+    // the start is only known once the INDEX line is read
+    let mut tracks: Vec<(CueTrack, bool)> = Vec::new();
+
+    for line in sheet.lines() {
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let current = tracks.last_mut().map(|(track, _)| track);
+        match (command, current) {
+            ("FILE", _) => {
+                // FILE "name.mp3" MP3, the name may contain spaces
+                let name = rest.rsplit_once(' ').map_or(rest, |(name, _)| name);
+                files.push(unquote(name));
+            }
+            ("TRACK", _) => {
+                if let Some(number) = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|nr| nr.parse().ok())
+                {
+                    let track = CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        start: Duration::ZERO,
+                    };
+                    tracks.push((track, false));
+                }
+            }
+            ("TITLE", Some(track)) => track.title = Some(unquote(rest)),
+            ("PERFORMER", Some(track)) => track.performer = Some(unquote(rest)),
+            ("INDEX", Some(_)) => {
+                let start = rest
+                    .split_once(' ')
+                    .filter(|(index, _)| *index == "01")
+                    .and_then(|(_, position)| parse_position(position.trim()));
+                if let (Some(start), Some((track, has_start))) = (start, tracks.last_mut()) {
+                    track.start = start;
+                    *has_start = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    let tracks = tracks
+        .into_iter()
+        .filter(|(_, has_start)| *has_start)
+        .map(|(track, _)| track)
+        .collect();
+    (files, tracks)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parses `mm:ss:ff`, a frame is 1/75 of a second.
+fn parse_position(position: &str) -> Option<Duration> {
+    let mut parts = position.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= 75 {
+        return None;
+    }
+    Some(
+        Duration::from_secs(minutes * 60 + seconds)
+            + Duration::from_nanos(frames * 1_000_000_000 / 75),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::parse_book;
+
+    #[test]
+    fn test_cue_sheet() {
+        let book = parse_book("../TestData/The Sign of the Four")
+            .unwrap()
+            .unwrap();
+        insta::assert_yaml_snapshot!(book.tracks);
+        assert_eq!(book.total_tracks, 3);
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(
+            parse_position("61:02:15"),
+            Some(Duration::from_millis(61 * 60_000 + 2_200))
+        );
+        assert_eq!(parse_position("00:60:00"), None);
+        assert_eq!(parse_position("00:00:75"), None);
+        assert_eq!(parse_position("00:00"), None);
+    }
+
+    #[test]
+    fn test_parse_cue() {
+        let (files, tracks) = parse_cue(
+            "FILE \"The Sign of the Four.mp3\" MP3\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"No Index\"\n",
+        );
+        assert_eq!(files, vec!["The Sign of the Four.mp3"]);
+        assert_eq!(
+            tracks,
+            vec![CueTrack {
+                number: 1,
+                title: None,
+                performer: None,
+                start: Duration::ZERO,
+            }]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::Book;
+
+/// What changed between two scans of a library, see [`diff_libraries`].
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct LibraryDiff {
+    /// Books only in the new scan.
+    pub added: Vec<Book>,
+    /// Books only in the old scan.
+    pub removed: Vec<Book>,
+    /// Books in both scans whose [`Book::content_hash`] differs.
+    pub changed: Vec<ChangedBook>,
+}
+
+impl LibraryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A book found in both scans with different metadata.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ChangedBook {
+    pub old: Book,
+    pub new: Book,
+    /// The names of the differing fields, like `year` or `tracks`.
+    pub fields: Vec<&'static str>,
+}
+
+/// Compares two scans of the same library.
+///
+/// Books are identified by title and authors, so a renamed book shows up as removed and
+/// added. Every list is ordered by title and authors.
+pub fn diff_libraries(old: &[Book], new: &[Book]) -> LibraryDiff {
+    let by_key = |books: &[Book]| -> BTreeMap<(String, BTreeSet<String>), Book> {
+        books
+            .iter()
+            .map(|book| ((book.title.clone(), book.author.clone()), book.clone()))
+            .collect()
+    };
+    let mut old = by_key(old);
+    let mut diff = LibraryDiff::default();
+
+    for (key, new) in by_key(new) {
+        match old.remove(&key) {
+            None => diff.added.push(new),
+            Some(old) if old.content_hash() != new.content_hash() => {
+                let fields = changed_fields(&old, &new);
+                diff.changed.push(ChangedBook { old, new, fields });
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old.into_values().collect();
+    diff
+}
+
+/// The fields covered by the content hash that differ, title and authors are the key.
+fn changed_fields(old: &Book, new: &Book) -> Vec<&'static str> {
+    let tracks = |book: &Book| {
+        let mut tracks: Vec<((u32, u32), String)> = book
+            .tracks
+            .iter()
+            .map(|track| (track.position(), track.title.clone()))
+            .collect();
+        tracks.sort_unstable();
+        tracks
+    };
+
+    let mut fields = Vec::new();
+    if old.reader() != new.reader() {
+        fields.push("reader");
+    }
+    if old.year != new.year {
+        fields.push("year");
+    }
+    if tracks(old) != tracks(new) {
+        fields.push("tracks");
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::Track;
+
+    fn book(title: &str, author: &str, year: i32, tracks: u32) -> Book {
+        let mut builder = Book::builder().title(title).author(author).year(year);
+        for nr in 1..=tracks {
+            builder = builder.track(
+                Track::builder(format!("Chapter {}", nr), nr)
+                    .reader("John Greenman")
+                    .build(),
+            );
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_diff_libraries() {
+        let old = vec![
+            book("The Adventures of Tom Sawyer", "Mark Twain", 1876, 2),
+            book("Winnetou I", "Karl May", 1893, 3),
+            book("The Time Machine", "H. G. Wells", 1895, 2),
+        ];
+        let new = vec![
+            book("The Time Machine", "H. G. Wells", 1895, 2),
+            book("The Adventures of Tom Sawyer", "Mark Twain", 1876, 3),
+            book("Winnetou I", "Karl May", 1893, 3),
+            book("Treasure Island", "Robert Louis Stevenson", 1883, 2),
+        ];
+
+        let diff = diff_libraries(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].title, "Treasure Island");
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].new.title, "The Adventures of Tom Sawyer");
+        assert_eq!(diff.changed[0].fields, vec!["tracks"]);
+
+        let diff = diff_libraries(&new, &old);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].title, "Treasure Island");
+
+        assert!(diff_libraries(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let old = book("Winnetou I", "Karl May", 1893, 3);
+        let new = book("Winnetou I", "Karl May", 1892, 3);
+        assert_eq!(changed_fields(&old, &new), vec!["year"]);
+
+        // a track moved to another disc
+        let mut new = old.clone();
+        new.tracks[2].disc = Some(2);
+        assert_ne!(old.content_hash(), new.content_hash());
+        assert_eq!(changed_fields(&old, &new), vec!["tracks"]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+/// Why a file couldn't be turned into a book or two books couldn't be merged.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("can't parse file: {path:?}")]
+    Unreadable {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("no Tag defined in File {0:?}")]
+    MissingTag(PathBuf),
+    #[error("no Title defined in File {0:?}")]
+    MissingTitle(PathBuf),
+    #[error("No artist defined in File {0:?}")]
+    MissingArtist(PathBuf),
+    #[error("No track defined in {0:?}")]
+    MissingTrackNumber(PathBuf),
+    #[error("No album defined in File {0:?}")]
+    MissingAlbum(PathBuf),
+    #[error("can't explain the tags of {0:?}, only ID3 is supported")]
+    UnsupportedFormat(PathBuf),
+    #[error("Title mismatch while merging: {left:?} and {right:?}")]
+    TitleMismatch { left: String, right: String },
+    #[error("Disc count mismatch while merging {title:?}: {left:?} and {right:?}")]
+    DiscsMismatch {
+        title: String,
+        left: Option<u32>,
+        right: Option<u32>,
+    },
+    #[error("Year mismatch while merging {title:?}: {left:?} and {right:?}")]
+    YearMismatch {
+        title: String,
+        left: Option<i32>,
+        right: Option<i32>,
+    },
+    #[error("Original year mismatch while merging {title:?}: {left:?} and {right:?}")]
+    OriginalYearMismatch {
+        title: String,
+        left: Option<i32>,
+        right: Option<i32>,
+    },
+    #[error("Genre mismatch while merging {title:?}: {left:?} and {right:?}")]
+    GenreMismatch {
+        title: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    #[error("Language mismatch while merging {title:?}: {left:?} and {right:?}")]
+    LanguageMismatch {
+        title: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    #[error("Publisher mismatch while merging {title:?}: {left:?} and {right:?}")]
+    PublisherMismatch {
+        title: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    #[error("ISBN mismatch while merging {title:?}: {left:?} and {right:?}")]
+    IsbnMismatch {
+        title: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    #[error("ASIN mismatch while merging {title:?}: {left:?} and {right:?}")]
+    AsinMismatch {
+        title: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+    #[error("Series mismatch while merging {title:?}: {left:?} and {right:?}")]
+    SeriesMismatch {
+        title: String,
+        left: (Option<String>, Option<f32>),
+        right: (Option<String>, Option<f32>),
+    },
+    #[error("Abridged mismatch while merging {title:?}: {left} and {right}")]
+    AbridgedMismatch {
+        title: String,
+        left: bool,
+        right: bool,
+    },
+}
+
+impl ParseError {
+    pub(crate) fn unreadable(
+        path: impl Into<PathBuf>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        ParseError::Unreadable {
+            path: path.into(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Whether reading the file again might work, like after a timeout of a network share.
+    pub fn is_transient(&self) -> bool {
+        let ParseError::Unreadable { source, .. } = self else {
+            return false;
+        };
+        // the I/O error is usually wrapped by the error of the tag library
+        let mut error: Option<&(dyn std::error::Error + 'static)> = Some(source.as_ref());
+        while let Some(e) = error {
+            if let Some(e) = e.downcast_ref::<io::Error>() {
+                return matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+                );
+            }
+            error = e.source();
+        }
+        false
+    }
+}
+
+/// Why a [`BookBuilder`](crate::book::BookBuilder) couldn't build a book.
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("the title of a book can't be empty")]
+    EmptyTitle,
+    #[error("total_tracks is {total_tracks}, but there are {tracks} tracks")]
+    TrackCountMismatch { total_tracks: u32, tracks: u32 },
+    #[error("a rating is 1 to 5 stars, not {0}")]
+    InvalidRating(u8),
+}
@@ -0,0 +1,198 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use id3::{Tag, TagLike};
+use serde::Serialize;
+
+use crate::{extended_text, read_id3, ParseError, ParseOptions, ReaderField};
+
+/// Where the value of one field of a book was taken from, see [`explain_file`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FieldSource {
+    /// A frame like `TALB`, `TXXX:NARRATOR` for a user defined one, with its raw value.
+    Frame {
+        id: String,
+        value: String,
+    },
+    /// The file or directory name, see [`ParseOptions::filename_fallback`].
+    FileName(String),
+    Missing,
+}
+
+impl fmt::Display for FieldSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldSource::Frame { id, value } => write!(f, "{} = {:?}", id, value),
+            FieldSource::FileName(name) => write!(f, "file name = {:?}", name),
+            FieldSource::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+/// The sources of the fields a book needs most, as [`parse_file`](crate::parse_file) would
+/// read them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMapping {
+    #[serde(serialize_with = "crate::book::serialize_path")]
+    pub path: PathBuf,
+    /// The title of the book, not of the track.
+    pub title: FieldSource,
+    pub author: FieldSource,
+    pub reader: FieldSource,
+    pub track: FieldSource,
+    pub year: FieldSource,
+}
+
+impl fmt::Display for FieldMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+        for (field, source) in [
+            ("title", &self.title),
+            ("author", &self.author),
+            ("reader", &self.reader),
+            ("track", &self.track),
+            ("year", &self.year),
+        ] {
+            writeln!(f, "  {:<6} <- {}", field, source)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows which frame of an mp3 feeds which field of its book, for finding out why a file
+/// doesn't parse or ends up in the wrong book.
+///
+/// Only ID3 tags are explained, other formats fail with [`ParseError::UnsupportedFormat`].
+pub fn explain_file<P: AsRef<Path>>(path: P) -> Result<FieldMapping, ParseError> {
+    explain_file_with_options(path, &ParseOptions::default())
+}
+
+/// Like [`explain_file`], with non-default [`ParseOptions`].
+pub fn explain_file_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<FieldMapping, ParseError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_ascii_lowercase);
+    // the formats parse_reader doesn't read as ID3
+    if let Some("m4b" | "m4a" | "mp4" | "flac" | "ogg" | "oga" | "opus" | "mka" | "webm") =
+        extension.as_deref()
+    {
+        return Err(ParseError::UnsupportedFormat(path.to_path_buf()));
+    }
+    let file = File::open(path).map_err(|e| ParseError::unreadable(path, e))?;
+    let tag = read_id3(&mut BufReader::new(file), path)?;
+
+    let reader = match options.reader_field {
+        ReaderField::Artist => frame(&tag, "TPE1"),
+        ReaderField::Composer => frame(&tag, "TCOM"),
+        ReaderField::Narrator => user_frame(&tag, "NARRATOR"),
+    };
+    let mut author = frame(&tag, "TPE2");
+    if author == FieldSource::Missing && options.artist_as_author {
+        author = frame(&tag, "TPE1");
+    }
+    let mut title = frame(&tag, "TALB");
+    if title == FieldSource::Missing {
+        if let Some(name) = options.fallback_album(path) {
+            title = FieldSource::FileName(name);
+        }
+    }
+    // ID3v2.4 replaced TYER by TDRC, a TYER that isn't a year doesn't count
+    let year = match tag.year() {
+        Some(_) => frame(&tag, "TYER"),
+        None => frame(&tag, "TDRC"),
+    };
+
+    Ok(FieldMapping {
+        path: path.to_path_buf(),
+        title,
+        author,
+        reader,
+        track: frame(&tag, "TRCK"),
+        year,
+    })
+}
+
+/// The text frame `id`, blank ones count as missing like they do when parsing.
+fn frame(tag: &Tag, id: &str) -> FieldSource {
+    match tag.get(id).and_then(|frame| frame.content().text()) {
+        Some(value) if !value.trim().is_empty() => FieldSource::Frame {
+            id: id.to_string(),
+            value: value.to_string(),
+        },
+        _ => FieldSource::Missing,
+    }
+}
+
+/// The `TXXX` frame with the given description.
+fn user_frame(tag: &Tag, description: &str) -> FieldSource {
+    match extended_text(tag, description) {
+        Some(value) if !value.trim().is_empty() => FieldSource::Frame {
+            id: format!("TXXX:{}", description),
+            value: value.to_string(),
+        },
+        _ => FieldSource::Missing,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_explain_file() {
+        let mapping = explain_file("../TestData/sherlock_holmes.mp3").unwrap();
+        let frame = |id: &str, value: &str| FieldSource::Frame {
+            id: id.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            mapping.title,
+            frame("TALB", "The Adventures of Sherlock Holmes")
+        );
+        assert_eq!(mapping.author, FieldSource::Missing);
+        assert_eq!(mapping.reader, frame("TPE1", "Sir Arthur Conan Doyle"));
+        // the tagger wrote number and total as two values instead of `2/12`
+        assert_eq!(mapping.track, frame("TRCK", "2\u{0}12"));
+        assert_eq!(mapping.year, FieldSource::Missing);
+        assert_eq!(
+            mapping.to_string(),
+            "../TestData/sherlock_holmes.mp3\n  title  <- TALB = \"The Adventures of Sherlock Holmes\"\n  author <- missing\n  reader <- TPE1 = \"Sir Arthur Conan Doyle\"\n  track  <- TRCK = \"2\\012\"\n  year   <- missing\n"
+        );
+        let json = serde_json::to_value(&mapping).unwrap();
+        assert_eq!(json["path"], "../TestData/sherlock_holmes.mp3");
+    }
+
+    #[test]
+    fn test_explain_file_with_options() {
+        let options = ParseOptions {
+            artist_as_author: true,
+            reader_field: ReaderField::Narrator,
+            ..Default::default()
+        };
+        let mapping =
+            explain_file_with_options("../TestData/sherlock_holmes.mp3", &options).unwrap();
+        assert_eq!(
+            mapping.author,
+            FieldSource::Frame {
+                id: "TPE1".to_string(),
+                value: "Sir Arthur Conan Doyle".to_string(),
+            }
+        );
+        assert_eq!(mapping.reader, FieldSource::Missing);
+
+        let err = explain_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b")
+            .unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportedFormat(_)));
+    }
+}
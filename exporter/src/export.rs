@@ -0,0 +1,433 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    iter,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Result, WrapErr};
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+use tracing::warn;
+
+use crate::{parse_all_books, Book, SkipPolicy};
+
+impl Book {
+    /// Serializes the book as a JSON object.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).wrap_err(format!("can't serialize book {:?}", self.title))
+    }
+
+    /// Renders the book as the `album.nfo` Kodi, Jellyfin and Plex read metadata from.
+    pub fn to_nfo(&self) -> String {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        self.write_nfo_album(&mut writer)
+            .expect("writing to a Vec can't fail");
+        String::from_utf8(writer.into_inner()).expect("quick-xml writes UTF-8")
+    }
+
+    /// Writes [`Book::to_nfo`] as `album.nfo` into `dir`.
+    pub fn write_nfo(&self, dir: &Path) -> Result<()> {
+        let path = dir.join("album.nfo");
+        std::fs::write(&path, self.to_nfo())
+            .wrap_err(format!("can't write NFO: {:?}", path.display()))
+    }
+
+    fn write_nfo_album(&self, writer: &mut Writer<Vec<u8>>) -> io::Result<()> {
+        writer.write_event(Event::Decl(BytesDecl::new(
+            "1.0",
+            Some("UTF-8"),
+            Some("yes"),
+        )))?;
+        writer
+            .create_element("album")
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("title")
+                    .write_text_content(BytesText::new(&self.title))?;
+                for author in &self.author {
+                    writer
+                        .create_element("artist")
+                        .write_text_content(BytesText::new(author))?;
+                }
+                if let Some(genre) = &self.genre {
+                    writer
+                        .create_element("genre")
+                        .write_text_content(BytesText::new(genre))?;
+                }
+                if let Some(year) = self.year {
+                    writer
+                        .create_element("year")
+                        .write_text_content(BytesText::new(&year.to_string()))?;
+                }
+                if let Some(description) = &self.description {
+                    writer
+                        .create_element("review")
+                        .write_text_content(BytesText::new(description))?;
+                }
+                for track in &self.tracks {
+                    writer
+                        .create_element("track")
+                        .write_inner_content(|writer| {
+                            writer
+                                .create_element("position")
+                                .write_text_content(BytesText::new(&track.track.to_string()))?;
+                            writer
+                                .create_element("title")
+                                .write_text_content(BytesText::new(&track.title))?;
+                            if let Some(duration) = track.duration {
+                                // Kodi expects minutes and seconds
+                                let seconds = duration.as_secs();
+                                writer.create_element("duration").write_text_content(
+                                    BytesText::new(&format!(
+                                        "{}:{:02}",
+                                        seconds / 60,
+                                        seconds % 60
+                                    )),
+                                )?;
+                            }
+                            Ok(())
+                        })?;
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+}
+
+/// Parses all books below `path` and serializes them as one JSON array.
+///
+/// Books that can't be parsed are skipped with a warning.
+pub fn parse_all_books_to_json(path: PathBuf) -> Result<String> {
+    let books: Vec<Book> = parse_all_books(path)
+        .filter_map(|res| {
+            if let Err(e) = res {
+                warn!("Error parsing: {:?}", e);
+                None
+            } else {
+                res.ok()
+            }
+        })
+        .collect();
+
+    serde_json::to_string(&books).wrap_err("can't serialize books")
+}
+
+/// Writes the books as JSON Lines, one object per line, as they come out of the iterator.
+///
+/// Unlike [`parse_all_books_to_json`] only one book is held at a time, so it suits
+/// `export_jsonl(parse_all_books(path), w)` on a large library. Books that can't be parsed
+/// are skipped with a warning.
+pub fn export_jsonl(books: impl Iterator<Item = Result<Book>>, w: impl Write) -> Result<()> {
+    export_jsonl_with_policy(books, w, SkipPolicy::Warn)
+}
+
+/// Like [`export_jsonl`], but `on_error` decides what happens to books that can't be
+/// parsed. With [`SkipPolicy::Error`] the export stops at the first one, the lines written
+/// so far stay.
+pub fn export_jsonl_with_policy(
+    books: impl Iterator<Item = Result<Book>>,
+    mut w: impl Write,
+    on_error: SkipPolicy,
+) -> Result<()> {
+    for book in books {
+        let book = match (book, on_error) {
+            (Ok(book), _) => book,
+            (Err(e), SkipPolicy::Error) => return Err(e),
+            (Err(e), SkipPolicy::Warn) => {
+                warn!("Error parsing: {:?}", e);
+                continue;
+            }
+            (Err(_), SkipPolicy::Ignore) => continue,
+        };
+        serde_json::to_writer(&mut w, &book)
+            .wrap_err(format!("can't serialize book {:?}", book.title))?;
+        w.write_all(b"\n")?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Renders the books as a plain text table with title, author, track count and year.
+pub fn books_to_table(books: &[Book]) -> String {
+    let header = [
+        "Title".to_string(),
+        "Author".to_string(),
+        "Tracks".to_string(),
+        "Year".to_string(),
+    ];
+    let rows: Vec<[String; 4]> = books
+        .iter()
+        .map(|book| {
+            [
+                book.title.clone(),
+                book.authors_joined(", "),
+                book.total_tracks.to_string(),
+                book.year
+                    .map_or_else(|| "-".to_string(), |year| year.to_string()),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 4];
+    for row in iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Writes the books as CSV, one row per book.
+///
+/// Authors and readers are joined with `; ` in sorted order, the duration is in seconds.
+pub fn export_csv(books: &[Book], w: impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(w);
+    writer.write_record([
+        "title",
+        "author",
+        "reader",
+        "year",
+        "track_count",
+        "duration_seconds",
+    ])?;
+    for book in books {
+        let join = |names: &BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join("; ");
+        writer
+            .write_record([
+                book.title.clone(),
+                join(&book.author),
+                join(&book.reader()),
+                book.year.map(|year| year.to_string()).unwrap_or_default(),
+                book.total_tracks.to_string(),
+                book.total_duration
+                    .map(|duration| duration.as_secs_f64().to_string())
+                    .unwrap_or_default(),
+            ])
+            .wrap_err(format!("can't write book {:?}", book.title))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Renders the books as an OPML outline, one outline per book holding one per track.
+///
+/// Track outlines link their file, tracks without a path are left out.
+pub fn export_opml(books: &[Book]) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("opml")
+        .with_attribute(("version", "2.0"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("head")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("title")
+                        .write_text_content(BytesText::new("Audiobooks"))?;
+                    Ok(())
+                })?;
+            writer
+                .create_element("body")
+                .write_inner_content(|writer| {
+                    for book in books {
+                        write_book_outline(writer, book)?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+    String::from_utf8(writer.into_inner()).wrap_err("OPML isn't valid UTF-8")
+}
+
+fn write_book_outline(writer: &mut Writer<Vec<u8>>, book: &Book) -> io::Result<()> {
+    let author = book.authors_joined(", ");
+    writer
+        .create_element("outline")
+        .with_attribute(("text", book.title.as_str()))
+        .with_attribute(("title", book.title.as_str()))
+        .with_attribute(("author", author.as_str()))
+        .write_inner_content(|writer| {
+            for track in &book.tracks {
+                if track.path.as_os_str().is_empty() {
+                    continue;
+                }
+                writer
+                    .create_element("outline")
+                    .with_attribute(("text", track.title.as_str()))
+                    .with_attribute(("type", "audio"))
+                    .with_attribute(("url", track.path.to_string_lossy().as_ref()))
+                    .write_empty()?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+    use crate::{parse_book, Track};
+
+    #[test]
+    fn test_to_json() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&book.to_json().unwrap()).unwrap();
+        insta::assert_json_snapshot!(json);
+    }
+
+    #[test]
+    fn test_to_json_names() {
+        // authors and readers are sets, but always a sorted array in the output
+        let book = Book::builder()
+            .title("The Gilded Age")
+            .author("Mark Twain")
+            .author("Charles Dudley Warner")
+            .track(
+                Track::builder("Chapter 1", 1)
+                    .reader("John Greenman")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&book.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json["author"],
+            serde_json::json!(["Charles Dudley Warner", "Mark Twain"])
+        );
+        assert_eq!(json["reader"], serde_json::json!(["John Greenman"]));
+    }
+
+    #[test]
+    fn test_to_nfo() {
+        let book = parse_book("../TestData/Penguin Island").unwrap().unwrap();
+        insta::assert_snapshot!(book.to_nfo());
+
+        let book = Book::builder()
+            .title("Dombey & Son <abridged>")
+            .author("Charles Dickens")
+            .description("The firm of Dombey & Son")
+            .build()
+            .unwrap();
+        let nfo = book.to_nfo();
+        assert!(nfo.contains("<title>Dombey &amp; Son &lt;abridged&gt;</title>"));
+        assert!(nfo.contains("<review>The firm of Dombey &amp; Son</review>"));
+    }
+
+    #[test]
+    fn test_export_jsonl() {
+        let books = [
+            parse_book("../TestData/Winnetou"),
+            parse_book("../TestData/Huckfinn"),
+        ]
+        .into_iter()
+        .map(|res| Ok(res.unwrap().unwrap()));
+        let mut out = Vec::new();
+        export_jsonl(books, &mut out).unwrap();
+        let jsonl = String::from_utf8(out).unwrap();
+        let titles: Vec<String> = jsonl
+            .lines()
+            .map(|line| {
+                let book: serde_json::Value = serde_json::from_str(line).unwrap();
+                book["title"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec!["Winnetou I", "The Adventures of Huckleberry Finn"]
+        );
+    }
+
+    #[test]
+    fn test_export_jsonl_errors() {
+        let books = || {
+            [
+                Err(eyre!("unreadable")),
+                Ok(parse_book("../TestData/Winnetou").unwrap().unwrap()),
+            ]
+            .into_iter()
+        };
+        let mut out = Vec::new();
+        export_jsonl(books(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+        let mut out = Vec::new();
+        assert!(export_jsonl_with_policy(books(), &mut out, SkipPolicy::Error).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_books_to_table() {
+        let mut books: Vec<Book> = parse_all_books(PathBuf::from("../TestData"))
+            .map(Result::unwrap)
+            .collect();
+        books.sort_by(|lhs, rhs| lhs.title.cmp(&rhs.title));
+        insta::assert_snapshot!(books_to_table(&books));
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let mut book = parse_book("../TestData/Winnetou").unwrap().unwrap();
+        book.title = "Winnetou I, Der rote Gentleman".to_string();
+        let mut out = Vec::new();
+        export_csv(&[book], &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "title,author,reader,year,track_count,duration_seconds"
+        );
+        assert!(
+            lines[1].starts_with("\"Winnetou I, Der rote Gentleman\",Karl May,"),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_export_opml() {
+        let book = parse_book("../TestData/Winnetou").unwrap().unwrap();
+        insta::assert_snapshot!(export_opml(&[book]).unwrap());
+    }
+
+    #[test]
+    fn test_export_opml_escaping() {
+        let mut book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        book.title = "Tom & Huck <Unabridged>".to_string();
+        let opml = export_opml(&[book]).unwrap();
+        assert!(
+            opml.contains("title=\"Tom &amp; Huck &lt;Unabridged&gt;\""),
+            "{}",
+            opml
+        );
+    }
+
+    #[test]
+    fn test_parse_all_books_to_json() {
+        let json = parse_all_books_to_json(PathBuf::from("../TestData")).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            json.as_array().unwrap().len(),
+            parse_all_books(PathBuf::from("../TestData")).count()
+        );
+    }
+}
@@ -1,107 +1,3955 @@
-use std::{collections::HashSet, fs::DirEntry, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
+    fs::File,
+    io::{BufReader, Read, Seek},
+    iter, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use color_eyre::eyre::{eyre, Result, WrapErr};
+use color_eyre::eyre::{Result, WrapErr};
 use id3::{Tag, TagLike};
-use serde::Serialize;
+use lofty::{
+    file::{AudioFile, FileType},
+    mp4::{AtomData, AtomIdent, Mp4File},
+    ogg::{OggPictureStorage, OpusFile, VorbisFile},
+    probe::Probe,
+    tag::{Accessor, ItemKey},
+};
+use rayon::prelude::*;
 use tracing::warn;
 
-pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<(Track, Tag)> {
-    let tag = Tag::read_from_path(&path)
-        .wrap_err(format!("can't parse file: {:?}", path.as_ref().display()))?;
-    tracing::debug!("read file {:?}", path.as_ref());
+#[cfg(feature = "zip")]
+mod archive;
+pub mod book;
+mod cache;
+mod chapters;
+mod concat;
+mod cue;
+mod diff;
+mod error;
+mod explain;
+mod export;
+#[cfg(feature = "logging")]
+mod logging;
+mod rename;
+mod sidecar;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "async")]
+mod stream;
+mod validate;
+mod write;
 
-    Ok((
-        Track {
+#[cfg(feature = "zip")]
+pub use archive::parse_book_from_zip;
+use book::Votes;
+pub use book::{Book, BookBuilder, Chapter, Cover, MergeStrategy, NameMatch, Track, TrackBuilder};
+pub use cache::{parse_book_cached, Cache};
+pub use concat::ConcatPlan;
+pub use diff::{diff_libraries, ChangedBook, LibraryDiff};
+pub use error::{BuildError, ParseError};
+pub use explain::{explain_file, explain_file_with_options, FieldMapping, FieldSource};
+pub use export::{
+    books_to_table, export_csv, export_jsonl, export_jsonl_with_policy, export_opml,
+    parse_all_books_to_json,
+};
+#[cfg(feature = "logging")]
+pub use logging::init_tracing;
+pub use rename::apply_renames;
+#[cfg(feature = "sqlite")]
+pub use sqlite::export_to_sqlite;
+#[cfg(feature = "async")]
+pub use stream::parse_all_books_async;
+pub use validate::{Issue, Severity, ValidationReport};
+
+/// Options changing how files are turned into books.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Derive a missing track title from the file name and a missing album from the
+    /// directory name instead of failing.
+    pub filename_fallback: bool,
+    /// How [`parse_book`] resolves files disagreeing on discs, year or genre.
+    pub merge_strategy: MergeStrategy,
+    /// Treat author and reader names differing only in case as the same person, the
+    /// spelling of the lowest numbered track is kept.
+    pub case_insensitive_names: bool,
+    /// The file extensions [`parse_book`] reads, compared case-insensitively. Other files,
+    /// like covers or playlists, are skipped silently.
+    pub extensions: Vec<String>,
+    /// The tag field the reader of a track is taken from.
+    pub reader_field: ReaderField,
+    /// Overlay the Audiobookshelf `metadata.json` of a book directory on its tags, the
+    /// sidecar wins where both have a value.
+    pub metadata_sidecar: bool,
+    /// How often reading a file is retried after a transient I/O error like a timeout.
+    pub io_retries: u32,
+    /// The wait before the first retry, it doubles for every further one.
+    pub retry_delay: Duration,
+    /// Skip files whose name starts with a `.`, like the `._` AppleDouble files macOS
+    /// leaves on foreign file systems.
+    pub skip_hidden: bool,
+    /// Strip the book title and track number prefixes LibriVox puts in track titles, see
+    /// [`Book::clean_track_titles`].
+    pub clean_track_titles: bool,
+    /// What [`parse_book_with_options`] does with files it can't parse,
+    /// [`SkipPolicy::Error`] fails [`parse_book_verbose_with_options`] as well.
+    pub on_skip: SkipPolicy,
+    /// Take the authors from the track artist of files without album artist, for books
+    /// tagged like music with the author as artist.
+    pub artist_as_author: bool,
+    /// Make [`parse_books_in_dir`] split a directory holding the files of several books
+    /// into one book per album.
+    pub split_mixed_dirs: bool,
+    /// Files whose album titles are equal after this function belong to the same book, like
+    /// [`normalize_title`] for titles differing by an `(Unabridged)`. The book keeps the
+    /// title of its first file.
+    pub title_normalizer: Option<fn(&str) -> String>,
+}
+
+/// Where the reader (narrator) of a track is stored in the tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReaderField {
+    /// The track artist (`TPE1`, `©ART`, `ARTIST`), the convention of LibriVox.
+    #[default]
+    Artist,
+    /// The composer (`TCOM`, `©wrt`, `COMPOSER`), used by Audible, which keeps the author
+    /// in the artist.
+    Composer,
+    /// A custom `NARRATOR` field (`TXXX:NARRATOR`, `----:com.apple.iTunes:NARRATOR`,
+    /// `NARRATOR`).
+    Narrator,
+}
+
+/// What happens to a file of a book that can't be parsed, like one without tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SkipPolicy {
+    /// Skip the file and log a warning.
+    #[default]
+    Warn,
+    /// Fail the whole book with the error of the first such file.
+    Error,
+    /// Skip the file silently, for folders mixing audio books with other recordings.
+    Ignore,
+}
+
+/// The extensions of all formats [`parse_file`] can read.
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "m4b", "m4a", "mp4", "flac", "ogg", "oga", "opus", "mka", "webm",
+];
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            filename_fallback: false,
+            merge_strategy: MergeStrategy::default(),
+            case_insensitive_names: false,
+            extensions: AUDIO_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            reader_field: ReaderField::default(),
+            metadata_sidecar: false,
+            io_retries: 0,
+            retry_delay: Duration::from_secs(1),
+            skip_hidden: true,
+            clean_track_titles: false,
+            on_skip: SkipPolicy::default(),
+            artist_as_author: false,
+            split_mixed_dirs: false,
+            title_normalizer: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Whether two album titles belong to the same book, see
+    /// [`ParseOptions::title_normalizer`].
+    fn same_title(&self, lhs: &str, rhs: &str) -> bool {
+        match self.title_normalizer {
+            _ if lhs == rhs => true,
+            Some(normalize) => normalize(lhs) == normalize(rhs),
+            None => false,
+        }
+    }
+
+    fn has_audio_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|extension| {
+                self.extensions
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+            })
+    }
+
+    /// The track title derived from the file name, `huckfinn_01_twain_apc_64kb` becomes
+    /// `huckfinn 01`.
+    fn fallback_title(&self, path: &Path) -> Option<String> {
+        if !self.filename_fallback {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        let parts: Vec<&str> = stem.split(['_', ' ']).filter(|p| !p.is_empty()).collect();
+        // keep everything up to the track number, the rest is usually reader and bitrate
+        let end = parts
+            .iter()
+            .position(|part| part.chars().all(|c| c.is_ascii_digit()))
+            .map_or(parts.len(), |idx| idx + 1);
+        Some(parts[..end].join(" ")).filter(|title| !title.is_empty())
+    }
+
+    /// The book title derived from the name of the directory containing the file.
+    fn fallback_album(&self, path: &Path) -> Option<String> {
+        if !self.filename_fallback {
+            return None;
+        }
+        Some(path.parent()?.file_name()?.to_str()?.to_string())
+    }
+}
+
+/// A reader for the metadata of one audio container format.
+///
+/// Every implementation maps its format onto a [`Book`] holding a single [`Track`], so
+/// files of different formats can be merged into the same book. `path` is only used to name
+/// the track and in errors, the content is read from `input`.
+trait TagSource {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError>;
+}
+
+/// Reads ID3 tags, used for mp3 files.
+struct Id3Source;
+
+impl TagSource for Id3Source {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        let tag = read_id3(input, path)?;
+        let stream = read_stream_info(input, path);
+        Self::book(&tag, stream, path, options)
+    }
+}
+
+impl Id3Source {
+    /// Maps a tag onto a book, `stream` comes from the audio frames after it.
+    fn book(
+        tag: &Tag,
+        stream: StreamInfo,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        let reader: Vec<String> = match options.reader_field {
+            ReaderField::Artist => tag.artists(),
+            ReaderField::Composer => tag
+                .get("TCOM")
+                .and_then(|frame| frame.content().text_values())
+                .map(Vec::from_iter),
+            ReaderField::Narrator => extended_text(tag, "NARRATOR").map(|name| vec![name]),
+        }
+        .into_iter()
+        .flatten()
+        .flat_map(split_names)
+        .collect();
+        if reader.is_empty() {
+            return Err(ParseError::MissingArtist(path.to_path_buf()));
+        }
+
+        let track = Track {
             title: tag
                 .title()
-                .ok_or_else(|| eyre!("no Title defined in File {:?}", path.as_ref()))?
-                .to_string(),
-            reader: tag
+                .and_then(non_blank)
+                .or_else(|| options.fallback_title(path))
+                .ok_or_else(|| ParseError::MissingTitle(path.to_path_buf()))?,
+            reader,
+            track: tag
+                .track()
+                .ok_or_else(|| ParseError::MissingTrackNumber(path.to_path_buf()))?,
+            disc: tag.disc(),
+            duration: stream.duration,
+            bitrate_kbps: stream.bitrate_kbps,
+            sample_rate_hz: stream.sample_rate_hz,
+            replaygain_track: extended_text(tag, "REPLAYGAIN_TRACK_GAIN").and_then(parse_gain),
+            transcript: tag
+                .lyrics()
+                .find(|lyrics| !lyrics.text.trim().is_empty())
+                .map(|lyrics| lyrics.text.clone()),
+            encoder: tag
+                .get("TSSE")
+                .and_then(|frame| frame.content().text())
+                .and_then(non_blank),
+            movement: unknown_text(tag, "MVNM").as_deref().and_then(non_blank),
+            movement_index: unknown_text(tag, "MVIN").as_deref().and_then(parse_number),
+            path: path.to_path_buf(),
+        };
+        // the comment without description is the synopsis, others are named notes
+        let description = tag
+            .comments()
+            .find(|comment| comment.description.is_empty() && !comment.text.trim().is_empty())
+            .map(|comment| comment.text.clone());
+        let cover = tag.pictures().next().map(|picture| Cover {
+            mime_type: picture.mime_type.clone(),
+            data: picture.data.clone(),
+        });
+        let disc_subtitles = disc_subtitle(
+            track.disc,
+            tag.get("TSST").and_then(|frame| frame.content().text()),
+        );
+        let mut author: BTreeSet<String> = tag
+            .album_artist()
+            .into_iter()
+            .flat_map(split_names)
+            .collect();
+        if author.is_empty() && options.artist_as_author {
+            author = tag
                 .artists()
-                .ok_or_else(|| eyre!("No artist defined in File {:?}", path.as_ref()))?
                 .into_iter()
-                .map(String::from)
+                .flatten()
+                .flat_map(split_names)
+                .collect();
+        }
+
+        Ok(Book {
+            title: tag
+                .album()
+                .and_then(non_blank)
+                .or_else(|| options.fallback_album(path))
+                .ok_or_else(|| ParseError::MissingAlbum(path.to_path_buf()))?,
+            author,
+            total_duration: track.duration,
+            replaygain_album: extended_text(tag, "REPLAYGAIN_ALBUM_GAIN").and_then(parse_gain),
+            rating: tag
+                .frames()
+                .filter_map(|frame| match frame.content() {
+                    id3::Content::Popularimeter(popm) => popm_stars(popm.rating),
+                    _ => None,
+                })
+                .max(),
+            // iTunes' flag as written by taggers that mirror it into ID3
+            gapless: extended_text(tag, "GAPLESS").and_then(parse_flag),
+            abridged: extended_text(tag, "ABRIDGED").and_then(parse_flag),
+            tracks: vec![track],
+            total_tracks: 1,
+            expected_tracks: tag.total_tracks(),
+            discs: tag.total_discs(),
+            disc_subtitles,
+            // ID3v2.4 replaced TYER by TDRC
+            year: tag
+                .year()
+                .or_else(|| tag.date_recorded().map(|date| date.year)),
+            original_year: tag
+                .get("TDOR")
+                .or_else(|| tag.get("TORY"))
+                .and_then(|frame| frame.content().text())
+                .and_then(parse_year),
+            genre: tag.genre().map(String::from),
+            language: tag
+                .get("TLAN")
+                .and_then(|frame| frame.content().text())
+                .and_then(normalize_language),
+            publisher: tag
+                .get("TPUB")
+                .and_then(|frame| frame.content().text())
+                .map(String::from),
+            isbn: extended_text(tag, "ISBN").and_then(|isbn| normalize_isbn(path, isbn)),
+            asin: extended_text(tag, "ASIN").and_then(|asin| normalize_asin(path, asin)),
+            series: extended_text(tag, "SERIES").map(String::from),
+            series_index: extended_text(tag, "SERIES-PART")
+                .and_then(|part| part.trim().parse().ok()),
+            description,
+            comments: tag.comments().map(|c| c.text.clone()).collect(),
+            cover,
+            chapters: id3_chapters(tag),
+            votes: Votes::default(),
+        })
+    }
+}
+
+/// Reads the ID3v2 tag, with the frames it lacks taken from an ID3v1 tag at the end of the
+/// file, so partially tagged files keep what the old tagger wrote. Where both have a value,
+/// ID3v2 wins.
+fn read_id3<R: Read + Seek>(input: &mut R, path: &Path) -> Result<Tag, ParseError> {
+    let v2 = Tag::read_from(&mut *input);
+    // any problem with the ID3v1 tag is as good as none
+    let v1 = id3::v1::Tag::read_from(&mut *input).ok().map(|mut v1| {
+        // old taggers pad with spaces
+        for value in [
+            &mut v1.title,
+            &mut v1.artist,
+            &mut v1.album,
+            &mut v1.comment,
+        ] {
+            *value = value.trim_end().to_string();
+        }
+        Tag::from(v1)
+    });
+    match (v2, v1) {
+        (Ok(mut tag), Some(v1)) => {
+            for frame in v1.frames() {
+                // ID3v2.4 has TDRC instead of TYER
+                let present = tag.get(frame.id()).is_some()
+                    || (frame.id() == "TYER" && tag.get("TDRC").is_some());
+                if !present {
+                    tag.add_frame(frame.clone());
+                }
+            }
+            Ok(tag)
+        }
+        (Ok(tag), None) => Ok(tag),
+        (
+            Err(id3::Error {
+                kind: id3::ErrorKind::NoTag,
+                ..
+            }),
+            Some(v1),
+        ) => Ok(v1),
+        (Err(e), _) => Err(ParseError::unreadable(path, e)),
+    }
+}
+
+/// Scales the 1 to 255 of a POPM frame to stars the way Windows Media Player writes them,
+/// 1, 64, 128, 196 and 255 are 1 to 5 stars. 0 means unrated.
+fn popm_stars(rating: u8) -> Option<u8> {
+    match rating {
+        0 => None,
+        1..=63 => Some(1),
+        64..=127 => Some(2),
+        128..=195 => Some(3),
+        196..=254 => Some(4),
+        255 => Some(5),
+    }
+}
+
+/// The value of the TXXX frame with the given description.
+fn extended_text<'a>(tag: &'a Tag, description: &str) -> Option<&'a str> {
+    tag.extended_texts()
+        .find(|text| text.description.eq_ignore_ascii_case(description))
+        .map(|text| text.value.as_str())
+}
+
+/// The text of a frame the id3 crate doesn't decode, like iTunes' `MVNM`, which doesn't
+/// start with a `T` like other text frames.
+fn unknown_text(tag: &Tag, id: &str) -> Option<String> {
+    let content = tag.get(id)?.content();
+    let id3::Content::Unknown(unknown) = content else {
+        return content.text().map(String::from);
+    };
+    let (encoding, text) = unknown.data.split_first()?;
+    let utf16 = |text: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = text
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let text = match (encoding, text) {
+        (0, text) => text.iter().copied().map(char::from).collect(),
+        (1, [0xFF, 0xFE, text @ ..]) => utf16(text, u16::from_le_bytes),
+        (1, [0xFE, 0xFF, text @ ..]) | (2, text) => utf16(text, u16::from_be_bytes),
+        (3, text) => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    Some(text.trim_end_matches('\0').to_string())
+}
+
+/// The properties of the audio stream of a file, as opposed to its tags.
+#[derive(Debug, Default, Clone, Copy)]
+struct StreamInfo {
+    duration: Option<Duration>,
+    bitrate_kbps: Option<u32>,
+    sample_rate_hz: Option<u32>,
+}
+
+impl StreamInfo {
+    fn new(duration: Duration, bitrate_kbps: Option<u32>, sample_rate_hz: Option<u32>) -> Self {
+        // lofty reports 0 if it can't tell
+        StreamInfo {
+            duration: Some(duration),
+            bitrate_kbps: bitrate_kbps.filter(|bitrate| *bitrate > 0),
+            sample_rate_hz: sample_rate_hz.filter(|rate| *rate > 0),
+        }
+    }
+}
+
+/// Reads the playing time, bitrate and sample rate from the audio stream, the ID3 tag
+/// doesn't carry them.
+fn read_stream_info<R: Read + Seek>(input: &mut R, path: &Path) -> StreamInfo {
+    let options = lofty::config::ParseOptions::new()
+        .read_tags(false)
+        .read_cover_art(false);
+    let file = input
+        .rewind()
+        .and_then(|()| Probe::new(input).guess_file_type())
+        .map_err(Into::into)
+        .and_then(|probe| probe.options(options).read());
+    match file {
+        Ok(file) => {
+            let properties = file.properties();
+            StreamInfo::new(
+                properties.duration(),
+                properties.audio_bitrate(),
+                properties.sample_rate(),
+            )
+        }
+        Err(e) => {
+            tracing::debug!("can't read stream properties of {:?}: {}", path, e);
+            StreamInfo::default()
+        }
+    }
+}
+
+/// The chapters of `CHAP` frames, ordered by their start.
+fn id3_chapters(tag: &Tag) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = tag
+        .chapters()
+        .map(|chapter| Chapter {
+            title: chapter
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .unwrap_or(&chapter.element_id)
+                .to_string(),
+            start: Duration::from_millis(chapter.start_time.into()),
+            end: Duration::from_millis(chapter.end_time.into()),
+        })
+        .collect();
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/// Reads the chapter list (`chpl`) or chapter track of an mp4 file, lofty supports neither.
+///
+/// Only the start of a chapter is stored, it ends where the next one starts or at `duration`.
+fn read_mp4_chapters<R: Read + Seek>(
+    input: &mut R,
+    path: &Path,
+    duration: Duration,
+) -> Result<Vec<Chapter>, ParseError> {
+    let config = mp4ameta::ReadConfig {
+        read_meta_items: false,
+        read_image_data: false,
+        read_audio_info: false,
+        ..mp4ameta::ReadConfig::DEFAULT
+    };
+    input
+        .rewind()
+        .map_err(|e| ParseError::unreadable(path, e))?;
+    let tag =
+        mp4ameta::Tag::read_with(input, &config).map_err(|e| ParseError::unreadable(path, e))?;
+    let starts = tag.chapters();
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|next| next.start)
+        .chain(iter::once(duration));
+
+    Ok(starts
+        .iter()
+        .zip(ends)
+        .map(|(chapter, end)| Chapter {
+            title: chapter.title.clone(),
+            start: chapter.start,
+            end,
+        })
+        .collect())
+}
+
+/// Reads the `ilst` atom through lofty, used for mp4 based containers like m4b.
+struct Mp4Source;
+
+impl TagSource for Mp4Source {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        let file = Mp4File::read_from(input, lofty::config::ParseOptions::new())
+            .map_err(|e| ParseError::unreadable(path, e))?;
+        let ilst = file
+            .ilst()
+            .ok_or_else(|| ParseError::MissingTag(path.to_path_buf()))?;
+        // freeform atoms like `----:com.apple.iTunes:SERIES` aren't part of the generic tag
+        let freeform = |name: &str| {
+            let ident = AtomIdent::Freeform {
+                mean: "com.apple.iTunes".into(),
+                name: name.into(),
+            };
+            ilst.get(&ident)?.data().find_map(|data| match data {
+                AtomData::UTF8(text) | AtomData::UTF16(text) => Some(text.clone()),
+                _ => None,
+            })
+        };
+        let tag = lofty::tag::Tag::from(ilst.clone());
+
+        let reader: Vec<String> = match options.reader_field {
+            ReaderField::Artist => tag
+                .get_strings(ItemKey::TrackArtist)
+                .flat_map(split_names)
                 .collect(),
+            ReaderField::Composer => tag
+                .get_strings(ItemKey::Composer)
+                .flat_map(split_names)
+                .collect(),
+            ReaderField::Narrator => freeform("NARRATOR")
+                .iter()
+                .flat_map(|n| split_names(n))
+                .collect(),
+        };
+        if reader.is_empty() {
+            return Err(ParseError::MissingArtist(path.to_path_buf()));
+        }
+
+        let track = Track {
+            title: tag
+                .title()
+                .and_then(|title| non_blank(&title))
+                .or_else(|| options.fallback_title(path))
+                .ok_or_else(|| ParseError::MissingTitle(path.to_path_buf()))?,
+            reader,
             track: tag
                 .track()
-                .ok_or_else(|| eyre!("No track defined in {:?}", path.as_ref()))?,
-            disc: tag.disc(),
-        },
-        tag,
-    ))
+                .ok_or_else(|| ParseError::MissingTrackNumber(path.to_path_buf()))?,
+            disc: tag.disk(),
+            duration: Some(file.properties().duration()),
+            bitrate_kbps: file
+                .properties()
+                .audio_bitrate()
+                .filter(|bitrate| *bitrate > 0),
+            sample_rate_hz: file.properties().sample_rate().filter(|rate| *rate > 0),
+            replaygain_track: freeform("replaygain_track_gain")
+                .as_deref()
+                .and_then(parse_gain),
+            transcript: tag
+                .get_string(ItemKey::Lyrics)
+                .filter(|lyrics| !lyrics.trim().is_empty())
+                .map(String::from),
+            encoder: tag.get_string(ItemKey::EncoderSoftware).and_then(non_blank),
+            movement: tag.get_string(ItemKey::Movement).and_then(non_blank),
+            movement_index: tag
+                .get_string(ItemKey::MovementNumber)
+                .and_then(parse_number),
+            path: path.to_path_buf(),
+        };
+        let chapters = read_mp4_chapters(input, path, file.properties().duration())?;
+        let disc_subtitles = disc_subtitle(track.disc, freeform("DISCSUBTITLE").as_deref());
+        let mut author: BTreeSet<String> = tag
+            .get_strings(ItemKey::AlbumArtist)
+            .flat_map(split_names)
+            .collect();
+        if author.is_empty() && options.artist_as_author {
+            author = tag
+                .get_strings(ItemKey::TrackArtist)
+                .flat_map(split_names)
+                .collect();
+        }
+
+        Ok(Book {
+            title: tag
+                .album()
+                .and_then(|album| non_blank(&album))
+                .or_else(|| options.fallback_album(path))
+                .ok_or_else(|| ParseError::MissingAlbum(path.to_path_buf()))?,
+            author,
+            total_duration: track.duration,
+            replaygain_album: freeform("replaygain_album_gain")
+                .as_deref()
+                .and_then(parse_gain),
+            rating: None,
+            gapless: ilst.get(&AtomIdent::Fourcc(*b"pgap")).and_then(|atom| {
+                atom.data().find_map(|data| match data {
+                    AtomData::Bool(gapless) => Some(*gapless),
+                    _ => None,
+                })
+            }),
+            abridged: freeform("ABRIDGED").as_deref().and_then(parse_flag),
+            tracks: vec![track],
+            total_tracks: 1,
+            expected_tracks: tag.track_total(),
+            discs: tag.disk_total(),
+            disc_subtitles,
+            year: tag.date().map(|date| i32::from(date.year)),
+            original_year: freeform("ORIGINALDATE")
+                .or_else(|| freeform("ORIGINALYEAR"))
+                .as_deref()
+                .and_then(parse_year),
+            genre: tag.genre().map(String::from),
+            language: tag
+                .get_string(ItemKey::Language)
+                .and_then(normalize_language),
+            publisher: freeform("PUBLISHER"),
+            isbn: freeform("ISBN").and_then(|isbn| normalize_isbn(path, &isbn)),
+            asin: freeform("ASIN").and_then(|asin| normalize_asin(path, &asin)),
+            series: freeform("SERIES"),
+            series_index: freeform("SERIES-PART").and_then(|part| part.trim().parse().ok()),
+            description: tag.get_string(ItemKey::Description).map(String::from),
+            comments: tag
+                .get_strings(ItemKey::Comment)
+                .map(String::from)
+                .collect(),
+            cover: tag.pictures().first().map(|picture| Cover {
+                mime_type: picture
+                    .mime_type()
+                    .map_or("image/jpeg", |mime_type| mime_type.as_str())
+                    .to_string(),
+                data: picture.data().to_vec(),
+            }),
+            chapters,
+            votes: Votes::default(),
+        })
+    }
 }
 
-pub fn parse_book<P: AsRef<Path>>(path: P) -> Result<Book> {
-    let mut tracks = std::fs::read_dir(&path)?
-        // only use entries that can be read
-        .filter_map(|res| {
-            if let Err(e) = res {
-                warn!("Error while collecting path: {:?}", &e);
-                None
-            } else {
-                res.ok()
+/// Reads Vorbis comments through metaflac, used for flac files.
+struct FlacSource;
+
+impl TagSource for FlacSource {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        let tag = metaflac::Tag::read_from(input).map_err(|e| ParseError::unreadable(path, e))?;
+        let comments = tag
+            .vorbis_comments()
+            .ok_or_else(|| ParseError::MissingTag(path.to_path_buf()))?;
+        let duration = tag
+            .get_streaminfo()
+            .filter(|info| info.sample_rate > 0)
+            .map(|info| {
+                Duration::from_secs_f64(info.total_samples as f64 / f64::from(info.sample_rate))
+            });
+        let cover = tag.pictures().next().map(|picture| Cover {
+            mime_type: picture.mime_type.clone(),
+            data: picture.data.clone(),
+        });
+        // metaflac doesn't know the bitrate, it depends on the size of the audio frames
+        let stream = StreamInfo {
+            duration,
+            ..read_stream_info(input, path)
+        };
+
+        book_from_vorbis_comments(
+            path,
+            options,
+            |key| comments.get(key).cloned().unwrap_or_default(),
+            stream,
+            cover,
+        )
+    }
+}
+
+/// Reads Vorbis comments through lofty, used for Ogg Vorbis and Opus files.
+struct OggSource;
+
+impl TagSource for OggSource {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        // `.ogg` files can hold either codec, the content decides
+        let probe = Probe::new(input)
+            .guess_file_type()
+            .map_err(|e| ParseError::unreadable(path, e))?;
+        let parse_options = lofty::config::ParseOptions::new();
+        let file_type = probe.file_type();
+        let mut reader = probe.into_inner();
+        let (comments, stream) = match file_type {
+            Some(FileType::Opus) => {
+                let file = OpusFile::read_from(&mut reader, parse_options)
+                    .map_err(|e| ParseError::unreadable(path, e))?;
+                let properties = file.properties();
+                // Opus always decodes at 48 kHz, the rate of the source says more
+                let stream = StreamInfo::new(
+                    properties.duration(),
+                    Some(properties.audio_bitrate()),
+                    Some(properties.input_sample_rate()),
+                );
+                (file.vorbis_comments().clone(), stream)
             }
-        })
-        // only use files, no symlinks or directories
-        .filter(|dir_entry| {
-            if let Ok(ft) = dir_entry.file_type() {
-                return ft.is_file();
+            _ => {
+                let file = VorbisFile::read_from(&mut reader, parse_options)
+                    .map_err(|e| ParseError::unreadable(path, e))?;
+                let properties = file.properties();
+                let stream = StreamInfo::new(
+                    properties.duration(),
+                    Some(properties.audio_bitrate()),
+                    Some(properties.sample_rate()),
+                );
+                (file.vorbis_comments().clone(), stream)
             }
-            false
+        };
+        let cover = comments.pictures().first().map(|(picture, _)| Cover {
+            mime_type: picture
+                .mime_type()
+                .map_or("image/jpeg", |mime_type| mime_type.as_str())
+                .to_string(),
+            data: picture.data().to_vec(),
+        });
+
+        book_from_vorbis_comments(
+            path,
+            options,
+            |key| comments.get_all(key).map(String::from).collect(),
+            stream,
+            cover,
+        )
+    }
+}
+
+/// Reads Matroska tags and chapters, used for mka and webm files.
+///
+/// Tags may follow the Matroska spec, with the book at target level 50 and the track at
+/// 30, or be written by ffmpeg, which puts everything at level 50 and names the album
+/// `ALBUM`.
+struct MatroskaSource;
+
+impl TagSource for MatroskaSource {
+    fn read<R: Read + Seek>(
+        input: &mut R,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Book, ParseError> {
+        let file = matroska::Matroska::open(input).map_err(|e| ParseError::unreadable(path, e))?;
+        let values = |track_level: bool, name: &str| -> Vec<String> {
+            file.tags
+                .iter()
+                .filter(|tag| {
+                    // a tag without target level is at level 50
+                    let level = tag.targets.as_ref().and_then(|t| t.target_type_value);
+                    track_level == (level == Some(matroska::TargetTypeValue::Chapter))
+                })
+                .flat_map(|tag| &tag.simple)
+                .filter(|simple| simple.name.eq_ignore_ascii_case(name))
+                .filter_map(|simple| match &simple.value {
+                    Some(matroska::TagValue::String(value)) => Some(value.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let book_value = |name: &str| values(false, name).into_iter().next();
+        // the track level wins, ffmpeg only writes the book level
+        let track_value = |name: &str| {
+            values(true, name)
+                .into_iter()
+                .next()
+                .or_else(|| book_value(name))
+        };
+
+        let reader_keys: &[&str] = match options.reader_field {
+            ReaderField::Artist => &["ARTIST"],
+            ReaderField::Composer => &["COMPOSER"],
+            ReaderField::Narrator => &["READ_BY", "NARRATOR"],
+        };
+        let reader: Vec<String> = reader_keys
+            .iter()
+            .find_map(|key| track_value(key))
+            .iter()
+            .flat_map(|names| split_names(names))
+            .collect();
+        if reader.is_empty() {
+            return Err(ParseError::MissingArtist(path.to_path_buf()));
+        }
+
+        let audio = file.audio_tracks().next();
+        let sample_rate_hz = audio.and_then(|track| match &track.settings {
+            matroska::Settings::Audio(audio) => Some(audio.sample_rate as u32),
+            _ => None,
+        });
+        let track = Track {
+            title: values(true, "TITLE")
+                .into_iter()
+                .next()
+                .or_else(|| file.info.title.clone())
+                .as_deref()
+                .and_then(non_blank)
+                .or_else(|| options.fallback_title(path))
+                .ok_or_else(|| ParseError::MissingTitle(path.to_path_buf()))?,
+            reader,
+            track: track_value("PART_NUMBER")
+                .as_deref()
+                .and_then(parse_number)
+                .ok_or_else(|| ParseError::MissingTrackNumber(path.to_path_buf()))?,
+            disc: None,
+            duration: file.info.duration,
+            bitrate_kbps: None,
+            sample_rate_hz: sample_rate_hz.filter(|rate| *rate > 0),
+            replaygain_track: track_value("REPLAYGAIN_GAIN")
+                .as_deref()
+                .and_then(parse_gain),
+            transcript: track_value("LYRICS").filter(|lyrics| !lyrics.trim().is_empty()),
+            encoder: track_value("ENCODER").as_deref().and_then(non_blank),
+            movement: None,
+            movement_index: None,
+            path: path.to_path_buf(),
+        };
+
+        // by the spec ARTIST at level 50 is the author if the track has its own
+        let author_value = book_value("ALBUM_ARTIST")
+            .or_else(|| book_value("ARTIST").filter(|_| !values(true, "ARTIST").is_empty()));
+        let mut author: BTreeSet<String> = author_value
+            .iter()
+            .flat_map(|names| split_names(names))
+            .collect();
+        if author.is_empty() && options.artist_as_author {
+            author = track_value("ARTIST")
+                .iter()
+                .flat_map(|names| split_names(names))
+                .collect();
+        }
+        let cover = file
+            .attachments
+            .iter()
+            .find(|attachment| attachment.mime_type.starts_with("image/"))
+            .map(|attachment| Cover {
+                mime_type: attachment.mime_type.clone(),
+                data: attachment.data.clone(),
+            });
+
+        Ok(Book {
+            title: book_value("ALBUM")
+                .or_else(|| book_value("TITLE"))
+                .or_else(|| file.info.title.clone())
+                .as_deref()
+                .and_then(non_blank)
+                .or_else(|| options.fallback_album(path))
+                .ok_or_else(|| ParseError::MissingAlbum(path.to_path_buf()))?,
+            author,
+            total_duration: track.duration,
+            replaygain_album: None,
+            rating: None,
+            gapless: None,
+            abridged: book_value("ABRIDGED").as_deref().and_then(parse_flag),
+            chapters: matroska_chapters(&file.chapters, track.duration),
+            tracks: vec![track],
+            total_tracks: 1,
+            expected_tracks: book_value("TOTAL_PARTS").as_deref().and_then(parse_number),
+            discs: None,
+            disc_subtitles: Vec::new(),
+            year: ["DATE_RELEASED", "DATE_RECORDED", "DATE"]
+                .iter()
+                .find_map(|key| track_value(key))
+                .as_deref()
+                .and_then(parse_year),
+            original_year: None,
+            genre: track_value("GENRE"),
+            language: audio
+                .and_then(|track| match &track.language {
+                    Some(matroska::Language::ISO639(language)) => Some(language.as_str()),
+                    _ => None,
+                })
+                .and_then(normalize_language),
+            publisher: book_value("PUBLISHER"),
+            isbn: book_value("ISBN").and_then(|isbn| normalize_isbn(path, &isbn)),
+            asin: None,
+            series: None,
+            series_index: None,
+            description: book_value("DESCRIPTION").or_else(|| book_value("SUMMARY")),
+            comments: values(false, "COMMENT"),
+            cover,
+            votes: Votes::default(),
+        })
+    }
+}
+
+/// The visible chapters of the default edition, or the first visible one if none is the
+/// default. A chapter without end lasts until the next one.
+fn matroska_chapters(
+    editions: &[matroska::ChapterEdition],
+    duration: Option<Duration>,
+) -> Vec<Chapter> {
+    let visible = || editions.iter().filter(|edition| !edition.hidden);
+    let Some(edition) = visible()
+        .find(|edition| edition.default)
+        .or_else(|| visible().next())
+    else {
+        return Vec::new();
+    };
+    // the crate reads a missing ChapterFlagEnabled as disabled, so only hidden counts
+    let chapters: Vec<&matroska::Chapter> = edition
+        .chapters
+        .iter()
+        .filter(|chapter| !chapter.hidden)
+        .collect();
+    let nexts = chapters
+        .iter()
+        .skip(1)
+        .map(|next| Some(next.time_start))
+        .chain([duration]);
+    chapters
+        .iter()
+        .zip(nexts)
+        .enumerate()
+        .map(|(idx, (chapter, next))| Chapter {
+            title: chapter.display.first().map_or_else(
+                || format!("Chapter {}", idx + 1),
+                |display| display.string.clone(),
+            ),
+            start: chapter.time_start,
+            end: chapter.time_end.or(next).unwrap_or(chapter.time_start),
+        })
+        .collect()
+}
+
+/// Trims the title and strips an `(Unabridged)` or `[Unabridged]` suffix, ignoring case.
+///
+/// ```
+/// use exporter::{normalize_title, ParseOptions};
+///
+/// assert_eq!(normalize_title("Dracula (Unabridged) "), "Dracula");
+/// let options = ParseOptions {
+///     title_normalizer: Some(normalize_title),
+///     ..Default::default()
+/// };
+/// ```
+pub fn normalize_title(title: &str) -> String {
+    let title = title.trim();
+    ["(unabridged)", "[unabridged]"]
+        .iter()
+        .find_map(|suffix| {
+            let idx = title.len().checked_sub(suffix.len())?;
+            title
+                .get(idx..)?
+                .eq_ignore_ascii_case(suffix)
+                .then(|| title[..idx].trim_end())
+        })
+        .unwrap_or(title)
+        .to_string()
+}
+
+/// Maps Vorbis comments onto a book, `all` returns every value of a key.
+fn book_from_vorbis_comments(
+    path: &Path,
+    options: &ParseOptions,
+    all: impl Fn(&str) -> Vec<String>,
+    stream: StreamInfo,
+    cover: Option<Cover>,
+) -> Result<Book, ParseError> {
+    let first = |key: &str| all(key).into_iter().next();
+
+    let reader_key = match options.reader_field {
+        ReaderField::Artist => "ARTIST",
+        ReaderField::Composer => "COMPOSER",
+        ReaderField::Narrator => "NARRATOR",
+    };
+    let reader: Vec<String> = all(reader_key)
+        .iter()
+        .flat_map(|a| split_names(a))
+        .collect();
+    if reader.is_empty() {
+        return Err(ParseError::MissingArtist(path.to_path_buf()));
+    }
+
+    let track = Track {
+        title: first("TITLE")
+            .as_deref()
+            .and_then(non_blank)
+            .or_else(|| options.fallback_title(path))
+            .ok_or_else(|| ParseError::MissingTitle(path.to_path_buf()))?,
+        reader,
+        track: first("TRACKNUMBER")
+            .as_deref()
+            .and_then(parse_number)
+            .ok_or_else(|| ParseError::MissingTrackNumber(path.to_path_buf()))?,
+        disc: first("DISCNUMBER").as_deref().and_then(parse_number),
+        duration: stream.duration,
+        bitrate_kbps: stream.bitrate_kbps,
+        sample_rate_hz: stream.sample_rate_hz,
+        replaygain_track: first("REPLAYGAIN_TRACK_GAIN")
+            .as_deref()
+            .and_then(parse_gain),
+        transcript: first("LYRICS").filter(|lyrics| !lyrics.trim().is_empty()),
+        encoder: first("ENCODER").as_deref().and_then(non_blank),
+        movement: first("MOVEMENTNAME").as_deref().and_then(non_blank),
+        movement_index: first("MOVEMENT").as_deref().and_then(parse_number),
+        path: path.to_path_buf(),
+    };
+
+    let disc_subtitles = disc_subtitle(track.disc, first("DISCSUBTITLE").as_deref());
+    let mut author: BTreeSet<String> = all("ALBUMARTIST")
+        .iter()
+        .flat_map(|a| split_names(a))
+        .collect();
+    if author.is_empty() && options.artist_as_author {
+        author = all("ARTIST").iter().flat_map(|a| split_names(a)).collect();
+    }
+
+    Ok(Book {
+        title: first("ALBUM")
+            .as_deref()
+            .and_then(non_blank)
+            .or_else(|| options.fallback_album(path))
+            .ok_or_else(|| ParseError::MissingAlbum(path.to_path_buf()))?,
+        author,
+        total_duration: track.duration,
+        replaygain_album: first("REPLAYGAIN_ALBUM_GAIN")
+            .as_deref()
+            .and_then(parse_gain),
+        rating: None,
+        gapless: None,
+        abridged: first("ABRIDGED").as_deref().and_then(parse_flag),
+        tracks: vec![track],
+        total_tracks: 1,
+        // TRACKNUMBER often holds the total as well, like 3/12
+        expected_tracks: first("TRACKTOTAL")
+            .or_else(|| first("TOTALTRACKS"))
+            .as_deref()
+            .and_then(parse_number)
+            .or_else(|| first("TRACKNUMBER").as_deref().and_then(parse_total)),
+        discs: first("DISCTOTAL")
+            .or_else(|| first("TOTALDISCS"))
+            .as_deref()
+            .and_then(parse_number),
+        disc_subtitles,
+        year: first("DATE").as_deref().and_then(parse_year),
+        original_year: first("ORIGINALDATE")
+            .or_else(|| first("ORIGINALYEAR"))
+            .as_deref()
+            .and_then(parse_year),
+        genre: first("GENRE"),
+        language: first("LANGUAGE").as_deref().and_then(normalize_language),
+        publisher: first("PUBLISHER").or_else(|| first("ORGANIZATION")),
+        isbn: first("ISBN").and_then(|isbn| normalize_isbn(path, &isbn)),
+        asin: first("ASIN").and_then(|asin| normalize_asin(path, &asin)),
+        series: first("SERIES"),
+        series_index: first("SERIES-PART").and_then(|part| part.trim().parse().ok()),
+        description: first("DESCRIPTION"),
+        comments: all("COMMENT"),
+        cover,
+        chapters: Vec::new(),
+        votes: Votes::default(),
+    })
+}
+
+/// The name of the disc a file belongs to, as list to merge with the other files of the book.
+fn disc_subtitle(disc: Option<u32>, subtitle: Option<&str>) -> Vec<(u32, String)> {
+    disc.zip(subtitle.and_then(non_blank)).into_iter().collect()
+}
+
+/// The gain of a ReplayGain tag like `-6.48 dB`.
+fn parse_gain(gain: &str) -> Option<f32> {
+    let gain = gain.trim();
+    let gain = gain
+        .get(gain.len().saturating_sub(2)..)
+        .filter(|unit| unit.eq_ignore_ascii_case("db"))
+        .map_or(gain, |_| &gain[..gain.len() - 2]);
+    gain.trim().parse().ok()
+}
+
+/// The year of a date, which is often complete, like `2019-03-01`.
+fn parse_year(date: &str) -> Option<i32> {
+    date.split('-').next()?.trim().parse().ok()
+}
+
+/// The trimmed `value`, `None` if nothing is left, so blank frames count as missing.
+fn non_blank(value: &str) -> Option<String> {
+    Some(value.trim())
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+/// Splits a tag value holding several names, like `Mark Twain/Charles Dickens`.
+fn split_names(value: &str) -> Vec<String> {
+    value
+        .split(['/', ';', '\0'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// ISO 639-2 codes with their ISO 639-1 counterpart and English name, for the languages
+/// audiobooks are commonly published in.
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("ara", "ar", "arabic"),
+    ("chi", "zh", "chinese"),
+    ("cze", "cs", "czech"),
+    ("dan", "da", "danish"),
+    ("dut", "nl", "dutch"),
+    ("eng", "en", "english"),
+    ("fin", "fi", "finnish"),
+    ("fre", "fr", "french"),
+    ("ger", "de", "german"),
+    ("gre", "el", "greek"),
+    ("heb", "he", "hebrew"),
+    ("hin", "hi", "hindi"),
+    ("hun", "hu", "hungarian"),
+    ("ita", "it", "italian"),
+    ("jpn", "ja", "japanese"),
+    ("kor", "ko", "korean"),
+    ("nor", "no", "norwegian"),
+    ("pol", "pl", "polish"),
+    ("por", "pt", "portuguese"),
+    ("rus", "ru", "russian"),
+    ("spa", "es", "spanish"),
+    ("swe", "sv", "swedish"),
+    ("tur", "tr", "turkish"),
+    ("ukr", "uk", "ukrainian"),
+];
+
+/// Normalizes a language to its ISO 639-2 code, `en`, `English` and `eng` all become `eng`.
+///
+/// Only the first of several languages is used, unknown values are kept lowercased.
+fn normalize_language(value: &str) -> Option<String> {
+    let value = value
+        .split(['\0', '/', ';', ','])
+        .next()?
+        .trim()
+        .to_lowercase();
+    // terminological codes like `deu` are mapped onto the bibliographic `ger` used by ID3
+    let value = match value.as_str() {
+        "zho" => "chi",
+        "ces" => "cze",
+        "nld" => "dut",
+        "fra" => "fre",
+        "deu" => "ger",
+        "ell" => "gre",
+        other => other,
+    };
+    if value.is_empty() {
+        return None;
+    }
+    let code = LANGUAGES
+        .iter()
+        .find(|(code, short, name)| [*code, *short, *name].contains(&value))
+        .map_or(value, |(code, _, _)| code);
+    Some(code.to_string())
+}
+
+/// Parses `1`/`0` and `true`/`false`.
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a track or disc number, ignoring a trailing total like in `3/12`.
+fn parse_number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Strips the hyphens of an ISBN-10 or ISBN-13, an invalid checksum drops the ISBN.
+fn normalize_isbn(path: &Path, value: &str) -> Option<String> {
+    let isbn: String = value
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if is_valid_isbn(&isbn) {
+        Some(isbn)
+    } else {
+        warn!("Ignoring invalid ISBN {:?} in {:?}", value, path);
+        None
+    }
+}
+
+/// Checks length and checksum of an ISBN-10 or ISBN-13 without hyphens.
+fn is_valid_isbn(isbn: &str) -> bool {
+    let digits: Vec<u32> = isbn
+        .chars()
+        .enumerate()
+        // only the last digit of an ISBN-10 may be an X, standing for 10
+        .map_while(|(idx, c)| match c {
+            'X' if idx == 9 && isbn.len() == 10 => Some(10),
+            _ => c.to_digit(10),
         })
-        // only each path is used
-        .map(|de| DirEntry::path(&de))
-        .map(parse_file)
-        .filter_map(|parse_res| {
-            if let Err(e) = parse_res {
+        .collect();
+    if digits.len() != isbn.chars().count() {
+        return false;
+    }
+    match digits.len() {
+        10 => {
+            let sum: u32 = digits
+                .iter()
+                .zip((1..=10).rev())
+                .map(|(digit, weight)| digit * weight)
+                .sum();
+            sum.is_multiple_of(11)
+        }
+        13 => {
+            let sum: u32 = digits
+                .iter()
+                .zip([1, 3].into_iter().cycle())
+                .map(|(digit, weight)| digit * weight)
+                .sum();
+            sum.is_multiple_of(10)
+        }
+        _ => false,
+    }
+}
+
+/// An ASIN consists of ten letters and digits, anything else is dropped.
+fn normalize_asin(path: &Path, value: &str) -> Option<String> {
+    let asin = value.trim().to_ascii_uppercase();
+    if asin.len() == 10 && asin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Some(asin)
+    } else {
+        warn!("Ignoring invalid ASIN {:?} in {:?}", value, path);
+        None
+    }
+}
+
+/// Parses the total of a number like `3/12`.
+fn parse_total(value: &str) -> Option<u32> {
+    value.split_once('/')?.1.trim().parse().ok()
+}
+
+/// Parses a single audio file into a [`Book`] containing only this file as a track.
+///
+/// The tag reader is chosen by the file extension, everything unknown is read as ID3.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Book, ParseError> {
+    parse_file_with_options(path, &ParseOptions::default())
+}
+
+/// Like [`parse_file`], with non-default [`ParseOptions`].
+pub fn parse_file_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<Book, ParseError> {
+    let path = path.as_ref();
+    let book = with_retries(options, || {
+        let file = File::open(path).map_err(|e| ParseError::unreadable(path, e))?;
+        parse_reader(BufReader::new(file), path, options)
+    })?;
+    tracing::debug!("read file {:?}", path);
+
+    Ok(book)
+}
+
+/// Like [`parse_file_with_options`], for a file that is already open or in memory.
+///
+/// `path` names the track and chooses the tag reader by its extension, it doesn't have to
+/// exist. I/O errors aren't retried, `input` can't be opened again.
+pub fn parse_reader<R: Read + Seek>(
+    mut input: R,
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<Book, ParseError> {
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("m4b" | "m4a" | "mp4") => Mp4Source::read(&mut input, path, options),
+        Some("flac") => FlacSource::read(&mut input, path, options),
+        Some("ogg" | "oga" | "opus") => OggSource::read(&mut input, path, options),
+        Some("mka" | "webm") => MatroskaSource::read(&mut input, path, options),
+        _ => Id3Source::read(&mut input, path, options),
+    }
+    .map(infer_abridged)
+}
+
+/// Maps an ID3 tag already in memory onto a [`Book`] like [`parse_file`] does for an mp3,
+/// without reading the file again.
+///
+/// `path` only names the track. Duration, bitrate and sample rate are unknown, they come
+/// from the audio rather than the tag.
+///
+/// ```
+/// use std::path::Path;
+///
+/// use exporter::book_from_tag;
+/// use id3::{Tag, TagLike};
+///
+/// let mut tag = Tag::new();
+/// tag.set_album("Emma");
+/// tag.set_title("Chapter 1");
+/// tag.set_artist("Elizabeth Klett");
+/// tag.set_track(1);
+/// let book = book_from_tag(&tag, Path::new("emma_01_austen.mp3"))?;
+/// assert_eq!(book.title(), "Emma");
+/// # Ok::<(), exporter::ParseError>(())
+/// ```
+pub fn book_from_tag(tag: &Tag, path: &Path) -> Result<Book, ParseError> {
+    book_from_tag_with_options(tag, path, &ParseOptions::default())
+}
+
+/// Like [`book_from_tag`], with non-default [`ParseOptions`].
+pub fn book_from_tag_with_options(
+    tag: &Tag,
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<Book, ParseError> {
+    Id3Source::book(tag, StreamInfo::default(), path, options).map(infer_abridged)
+}
+
+/// Falls back to the title for whether the book is abridged.
+fn infer_abridged(mut book: Book) -> Book {
+    book.abridged = book.abridged.or_else(|| abridged_from_title(&book.title));
+    book
+}
+
+/// Whether a title like `Emma (Unabridged)` says if the recording is abridged.
+fn abridged_from_title(title: &str) -> Option<bool> {
+    let title = title.to_lowercase();
+    if title.contains("unabridged") {
+        Some(false)
+    } else if title.contains("abridged") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Calls `read` until it succeeds, fails with an error that isn't transient or
+/// [`ParseOptions::io_retries`] are used up.
+fn with_retries<T>(
+    options: &ParseOptions,
+    mut read: impl FnMut() -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let mut delay = options.retry_delay;
+    let mut retries = 0;
+    loop {
+        match read() {
+            Err(e) if e.is_transient() && retries < options.io_retries => {
+                warn!("{}, retrying in {:?}", e, delay);
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+                retries += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Parses all files in the directory and merges them into one [`Book`].
+///
+/// Unlike [`parse_all_books`] subdirectories are left alone, so it's the way to re-scan a
+/// single known book. Missing and duplicate tracks are warned about the same way.
+///
+/// Returns `None` if the directory contains no parseable file.
+///
+/// ```
+/// let book = exporter::parse_book("../TestData/Huckfinn")?.expect("the folder has mp3s");
+/// assert_eq!(book.title(), "The Adventures of Huckleberry Finn");
+/// assert!(book.is_complete());
+/// # Ok::<(), color_eyre::eyre::Report>(())
+/// ```
+pub fn parse_book<P: AsRef<Path>>(path: P) -> Result<Option<Book>> {
+    parse_book_with_options(path, &ParseOptions::default())
+}
+
+/// Like [`parse_book`], with non-default [`ParseOptions`].
+pub fn parse_book_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<Option<Book>> {
+    let Some((book, skipped)) = parse_book_verbose_with_options(path, options)? else {
+        return Ok(None);
+    };
+    report_skipped(skipped, options)?;
+    Ok(Some(book))
+}
+
+/// Handles the files skipped while parsing a book as [`ParseOptions::on_skip`] says.
+fn report_skipped(skipped: Vec<ParseError>, options: &ParseOptions) -> Result<(), ParseError> {
+    match options.on_skip {
+        SkipPolicy::Warn => {
+            for e in skipped {
                 warn!("Error parsing: {:?}", e);
-                None
-            } else {
-                parse_res.ok()
             }
-        });
+        }
+        SkipPolicy::Error => {
+            if let Some(e) = skipped.into_iter().next() {
+                return Err(e);
+            }
+        }
+        SkipPolicy::Ignore => {}
+    }
+    Ok(())
+}
+
+/// Like [`parse_book_with_options`], but a directory holding the files of several books
+/// yields all of them if [`ParseOptions::split_mixed_dirs`] is set, instead of failing on
+/// their different titles.
+///
+/// The files are grouped by album, the books are in the order of their first file name. The
+/// sidecar and cue sheet of the directory are only used if it turns out to hold one book.
+pub fn parse_books_in_dir<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Vec<Book>> {
+    let path = path.as_ref();
+    if !options.split_mixed_dirs {
+        return Ok(parse_book_with_options(path, options)?
+            .into_iter()
+            .collect());
+    }
+
+    let mut skipped = Vec::new();
+    let files = audio_files(path, options, &mut skipped)?;
+    let mut albums: Vec<Vec<Book>> = Vec::new();
+    for book in parse_files(files, options, &mut skipped)? {
+        match albums
+            .iter_mut()
+            .find(|album| options.same_title(&album[0].title, &book.title))
+        {
+            Some(album) => album.push(book),
+            None => albums.push(vec![book]),
+        }
+    }
+    if albums.len() > 1 {
+        let titles: Vec<&str> = albums.iter().map(|album| album[0].title.as_str()).collect();
+        warn!(
+            "{:?} holds the files of several books, splitting it into {:?}",
+            path.display(),
+            titles
+        );
+    }
 
-    todo!("good way to reduce file infos to book");
+    let mut books = Vec::new();
+    let single = albums.len() == 1;
+    for album in albums {
+        if let Some(book) = merge_books(album, options)? {
+            books.push(finish_book(book, path, single, options, &mut skipped));
+        }
+    }
+    report_skipped(skipped, options)?;
+    Ok(books)
 }
 
-#[cfg(test)]
-mod test {
+/// Like [`parse_book`], but also returns why files of the directory were skipped.
+pub fn parse_book_verbose<P: AsRef<Path>>(path: P) -> Result<Option<(Book, Vec<ParseError>)>> {
+    parse_book_verbose_with_options(path, &ParseOptions::default())
+}
 
-    use super::*;
+/// Like [`parse_book_verbose`], with non-default [`ParseOptions`].
+pub fn parse_book_verbose_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<Option<(Book, Vec<ParseError>)>> {
+    let path = path.as_ref();
+    let mut skipped = Vec::new();
+    let files = audio_files(path, options, &mut skipped)?;
+    let Some(book) = merge_files(files, options, &mut skipped)? else {
+        return Ok(None);
+    };
+    let book = finish_book(book, path, true, options, &mut skipped);
 
-    #[test]
-    fn test_parse_single_file() {
-        let (track, _) = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
-        insta::assert_yaml_snapshot!(track);
+    Ok(Some((book, skipped)))
+}
 
-        let (track, _) = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
-        insta::assert_yaml_snapshot!(track);
+/// The audio files in `dir`, entries that can't be read are added to `skipped`.
+fn audio_files(
+    dir: &Path,
+    options: &ParseOptions,
+    skipped: &mut Vec<ParseError>,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                skipped.push(ParseError::unreadable(dir, e));
+                continue;
+            }
+        };
+        // only use audio files, no symlinks or directories
+        if !entry.file_type().is_ok_and(|ft| ft.is_file())
+            || !options.has_audio_extension(&entry.path())
+            || (options.skip_hidden && entry.file_name().as_encoded_bytes().starts_with(b"."))
+        {
+            continue;
+        }
+        files.push(entry.path());
+    }
+    // the order of a directory listing depends on the file system
+    files.sort();
+    Ok(files)
+}
 
-        let (track, _) =
-            parse_file("../TestData/Penguin Island/penguin_island_01_france_64kb.mp3").unwrap();
-        insta::assert_yaml_snapshot!(track);
+/// Applies the cue sheet and sidecar of `dir` to a book read from it, if it's the only
+/// book there, and cleans the track titles.
+fn finish_book(
+    mut book: Book,
+    dir: &Path,
+    only_book: bool,
+    options: &ParseOptions,
+    skipped: &mut Vec<ParseError>,
+) -> Book {
+    if only_book {
+        if let Err(e) = cue::split_by_cue(&mut book, dir) {
+            skipped.push(e);
+        }
+        if let Err(e) = chapters::apply_chapters_file(&mut book, dir) {
+            skipped.push(e);
+        }
+        if options.metadata_sidecar {
+            // a broken sidecar is reported like a broken file, the tags are still good
+            if let Err(e) = sidecar::apply_sidecar(&mut book, dir) {
+                skipped.push(e);
+            }
+        }
+    }
+    if options.clean_track_titles {
+        book.clean_track_titles();
+    }
+    warn_incomplete(&book);
+    book
+}
 
-        let (track, _) = parse_file("../TestData/Winnetou/winnetou1_01_may_64kb.mp3").unwrap();
-        insta::assert_yaml_snapshot!(track);
+/// Parses exactly the given files and merges them into one [`Book`], wherever they are.
+///
+/// Files that can't be parsed are skipped with a warning like in [`parse_book`], `None`
+/// means no file could be parsed.
+pub fn parse_book_from_files(files: impl IntoIterator<Item = PathBuf>) -> Option<Result<Book>> {
+    let mut skipped = Vec::new();
+    let book = merge_files(files, &ParseOptions::default(), &mut skipped);
+    for e in skipped {
+        warn!("Error parsing: {:?}", e);
+    }
+    match book {
+        Ok(Some(book)) => {
+            warn_incomplete(&book);
+            Some(Ok(book))
+        }
+        Ok(None) => None,
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+/// Parses `files` and merges them in track order, the files that can't be parsed are added
+/// to `skipped`.
+fn merge_files(
+    files: impl IntoIterator<Item = PathBuf>,
+    options: &ParseOptions,
+    skipped: &mut Vec<ParseError>,
+) -> Result<Option<Book>, ParseError> {
+    merge_books(parse_files(files, options, skipped)?, options)
+}
+
+/// Parses every file on its own, the files that can't be parsed are added to `skipped`.
+fn parse_files(
+    files: impl IntoIterator<Item = PathBuf>,
+    options: &ParseOptions,
+    skipped: &mut Vec<ParseError>,
+) -> Result<Vec<Book>, ParseError> {
+    let mut books = Vec::new();
+    for file in files {
+        match parse_file_with_options(file, options) {
+            Ok(book) => books.push(book),
+            // there's no point in reading the rest
+            Err(e) if options.on_skip == SkipPolicy::Error => return Err(e),
+            Err(e) => skipped.push(e),
+        }
+    }
+    Ok(books)
+}
+
+/// Merges the books of single files in track order.
+fn merge_books(mut books: Vec<Book>, options: &ParseOptions) -> Result<Option<Book>, ParseError> {
+    if options.case_insensitive_names {
+        unify_name_casing(&mut books);
+    }
+
+    let mut books = books.into_iter();
+    let first = match books.next() {
+        Some(book) => book,
+        None => return Ok(None),
+    };
+    let mut book = books.try_fold(first, |lhs, mut rhs| {
+        if options.same_title(&lhs.title, &rhs.title) {
+            rhs.title.clone_from(&lhs.title);
+        }
+        Book::merge_with(lhs, rhs, options.merge_strategy)
+    })?;
+    book.tracks.sort_by_key(Track::position);
+    Ok(Some(book))
+}
+
+/// Warns about missing and duplicate tracks.
+fn warn_incomplete(book: &Book) {
+    let missing = book.missing_tracks();
+    if !missing.is_empty() {
+        warn!("Book {:?} is missing tracks {:?}", book.title, missing);
+    }
+    let missing = book.missing_discs();
+    if !missing.is_empty() {
+        warn!("Book {:?} is missing discs {:?}", book.title, missing);
+    }
+    let duplicates = book.duplicate_tracks();
+    if !duplicates.is_empty() {
+        warn!(
+            "Book {:?} has duplicate tracks {:?}",
+            book.title, duplicates
+        );
+    }
+}
+
+/// Replaces author and reader names by the first spelling seen for them, ignoring case.
+fn unify_name_casing(books: &mut [Book]) {
+    books.sort_by_key(|book| book.tracks.iter().map(Track::position).min());
+    let mut spellings: HashMap<String, String> = HashMap::new();
+    let mut canonical = |name: String| {
+        spellings
+            .entry(name.trim().to_lowercase())
+            .or_insert(name)
+            .clone()
+    };
+    for book in books {
+        book.author = mem::take(&mut book.author)
+            .into_iter()
+            .map(&mut canonical)
+            .collect();
+        for track in &mut book.tracks {
+            track.reader = mem::take(&mut track.reader)
+                .into_iter()
+                .map(&mut canonical)
+                .collect();
+        }
     }
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-pub struct Track {
-    title: String,
-    reader: Vec<String>,
-    track: u32,
-    disc: Option<u32>,
+/// Walks the directory tree and parses every directory containing audio files as a book.
+///
+/// Symlinked directories are followed, but every real directory is parsed only once.
+/// Directories containing an [`IGNORE_MARKER`] file are skipped.
+/// A directory that can't be read yields an error, the walk continues with its siblings.
+pub fn parse_all_books(path: PathBuf) -> Box<dyn Iterator<Item = Result<Book>>> {
+    parse_all_books_with_depth(path, usize::MAX)
+}
+
+/// Like [`parse_all_books`] for a library spread over several roots, like drives, walked
+/// one after the other.
+///
+/// A book found below two roots is returned twice, see [`merge_libraries`] to combine them.
+pub fn parse_all_books_multi(
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> Box<dyn Iterator<Item = Result<Book>>> {
+    let paths: Vec<PathBuf> = paths.into_iter().collect();
+    Box::new(paths.into_iter().flat_map(parse_all_books))
+}
+
+/// Like [`parse_all_books`], but collects the books in a stable order instead of the order
+/// of the file system, for listings that don't change from one scan to the next.
+///
+/// The books are sorted by their [`Book::primary_author`], series, position in the series
+/// and title, books without author or series come first. Books that can't be parsed are
+/// logged and left out, only a `path` that can't be read is an error.
+pub fn parse_all_books_sorted(path: PathBuf) -> Result<Vec<Book>> {
+    std::fs::read_dir(&path).wrap_err(format!("can't read directory: {:?}", path.display()))?;
+    let mut books: Vec<Book> = parse_all_books(path)
+        .filter_map(|res| res.map_err(|e| warn!("{:?}", e)).ok())
+        .collect();
+    books.sort_by(library_order);
+    Ok(books)
+}
+
+/// The order of [`parse_all_books_sorted`].
+fn library_order(lhs: &Book, rhs: &Book) -> std::cmp::Ordering {
+    (lhs.primary_author(), lhs.series.as_deref())
+        .cmp(&(rhs.primary_author(), rhs.series.as_deref()))
+        .then_with(|| match (lhs.series_index, rhs.series_index) {
+            (Some(lhs), Some(rhs)) => lhs.total_cmp(&rhs),
+            (lhs, rhs) => lhs.is_some().cmp(&rhs.is_some()),
+        })
+        .then_with(|| lhs.title.cmp(&rhs.title))
+}
+
+/// Like [`parse_all_books`], but doesn't descend more than `max_depth` directories below `path`.
+///
+/// With a depth of 0 only `path` itself is parsed as a book, with 1 also its direct
+/// subdirectories and so on.
+pub fn parse_all_books_with_depth(
+    path: PathBuf,
+    max_depth: usize,
+) -> Box<dyn Iterator<Item = Result<Book>>> {
+    Box::new(DirWalk::new(path, max_depth).filter_map(|dir| dir.and_then(parse_book).transpose()))
+}
+
+/// Like [`parse_all_books`], but calls `on_book` with every directory right before it's parsed,
+/// for example to drive a progress bar.
+///
+/// The callback sees every directory of the walk, including the ones that turn out to contain
+/// no audio files.
+pub fn parse_all_books_with_progress<F>(
+    path: PathBuf,
+    mut on_book: F,
+) -> impl Iterator<Item = Result<Book>>
+where
+    F: FnMut(&Path),
+{
+    DirWalk::new(path, usize::MAX).filter_map(move |dir| {
+        dir.and_then(|dir| {
+            on_book(&dir);
+            parse_book(dir)
+        })
+        .transpose()
+    })
+}
+
+/// Like [`parse_all_books`], but fails on the first problem instead of skipping it, for
+/// checking the tags of a library.
+///
+/// Unreadable directories, files that can't be parsed and books whose files can't be merged
+/// are all errors.
+pub fn parse_all_books_strict(path: PathBuf) -> Result<Vec<Book>> {
+    let mut books = Vec::new();
+    for dir in DirWalk::new(path, usize::MAX) {
+        if let Some((book, skipped)) = parse_book_verbose(dir?)? {
+            if let Some(e) = skipped.into_iter().next() {
+                return Err(e.into());
+            }
+            books.push(book);
+        }
+    }
+    Ok(books)
+}
+
+/// A directory found by [`parse_all_books_with_empty_dirs`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanEntry {
+    Book(Box<Book>),
+    /// A directory with files, like a cover or a `.cue` sheet, but none that could be parsed
+    /// as audio, usually a book whose download failed.
+    EmptyBookDir(PathBuf),
+}
+
+/// Like [`parse_all_books`], but also reports the directories that hold files without any
+/// being parseable audio, for auditing a library.
+///
+/// Directories holding nothing but subdirectories or hidden files, like the folder of an
+/// author, aren't reported.
+pub fn parse_all_books_with_empty_dirs(path: PathBuf) -> Vec<Result<ScanEntry>> {
+    DirWalk::new(path, usize::MAX)
+        .filter_map(|dir| {
+            dir.and_then(|dir| match parse_book(&dir)? {
+                Some(book) => Ok(Some(ScanEntry::Book(Box::new(book)))),
+                None if has_visible_files(&dir)? => Ok(Some(ScanEntry::EmptyBookDir(dir))),
+                None => Ok(None),
+            })
+            .transpose()
+        })
+        .collect()
+}
+
+/// Whether `dir` directly contains a file whose name doesn't start with a dot.
+fn has_visible_files(dir: &Path) -> Result<bool> {
+    for entry in
+        std::fs::read_dir(dir).wrap_err(format!("can't read directory: {:?}", dir.display()))?
+    {
+        let path = entry
+            .wrap_err(format!("can't read directory: {:?}", dir.display()))?
+            .path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+        if path.is_file() && !hidden {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Like [`parse_all_books`], but parses the books in parallel.
+///
+/// The directory tree is walked first, then every directory is parsed on the rayon thread pool.
+pub fn par_parse_all_books(path: PathBuf) -> Vec<Result<Book>> {
+    let dirs: Vec<Result<PathBuf>> = DirWalk::new(path, usize::MAX).collect();
+    dirs.into_par_iter()
+        .filter_map(|dir| dir.and_then(parse_book).transpose())
+        .collect()
+}
+
+/// Directories containing a file of this name are skipped together with everything below
+/// them, like `.nomedia`.
+pub const IGNORE_MARKER: &str = ".abdb-ignore";
+
+/// Walks `path` and all directories below it, parents before their children. Unreadable
+/// directories are returned as errors.
+///
+/// The walk keeps its own stack instead of recursing, so deep trees can't overflow the
+/// call stack, and only reads a directory once the one before it was returned.
+struct DirWalk {
+    /// The directories still to visit with the depth left below them, the next on top.
+    pending: Vec<(PathBuf, usize)>,
+    /// The canonical paths already visited, so a directory reachable through several
+    /// symlinks is only returned once.
+    visited: HashSet<PathBuf>,
+}
+
+impl DirWalk {
+    fn new(path: PathBuf, max_depth: usize) -> Self {
+        DirWalk {
+            pending: vec![(path, max_depth)],
+            visited: HashSet::new(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, PartialEq, Eq)]
-pub struct Book {
-    title: String,
-    author: HashSet<String>,
-    reader: HashSet<String>,
-    tracks: Vec<Track>,
-    total_tracks: u32,
-    discs: Option<u32>,
-    year: Option<i32>,
-    comments: Vec<String>,
+impl Iterator for DirWalk {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, max_depth) = self.pending.pop()?;
+            let canonical = match std::fs::canonicalize(&path) {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    return Some(
+                        Err(e).wrap_err(format!("can't read directory: {:?}", path.display())),
+                    )
+                }
+            };
+            if self.visited.contains(&canonical) {
+                // a symlink pointing at an ancestor would otherwise be followed forever
+                warn!(
+                    "Skipping {:?}, it leads to the already visited {:?}",
+                    path.display(),
+                    canonical.display()
+                );
+                continue;
+            }
+            self.visited.insert(canonical);
+            if path.join(IGNORE_MARKER).exists() {
+                tracing::debug!("ignoring {:?}", path);
+                continue;
+            }
+            let entries = match std::fs::read_dir(&path) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    return Some(
+                        Err(e).wrap_err(format!("can't read directory: {:?}", path.display())),
+                    )
+                }
+            };
+            if max_depth > 0 {
+                let children: Vec<PathBuf> = entries
+                    .filter_map(|entry| match entry {
+                        // `Path::is_dir` follows symlinks, unlike the file type of the entry
+                        Ok(entry) if entry.path().is_dir() => Some(entry.path()),
+                        Ok(_) => None,
+                        Err(e) => {
+                            warn!("Error while collecting path: {:?}", &e);
+                            None
+                        }
+                    })
+                    .collect();
+                // reversed, so the first child is visited next
+                self.pending.extend(
+                    children
+                        .into_iter()
+                        .rev()
+                        .map(|child| (child, max_depth - 1)),
+                );
+            }
+            return Some(Ok(path));
+        }
+    }
+}
+
+/// Merges books found more than once, for example in a copied or aliased directory.
+///
+/// Books are the same if title, authors and year match. Tracks present in both copies are
+/// kept once, books that can't be merged are kept side by side.
+pub fn dedupe_books(books: Vec<Book>) -> Vec<Book> {
+    let mut deduped: Vec<Book> = Vec::with_capacity(books.len());
+    for book in books {
+        let duplicate = deduped.iter_mut().find(|other| same_book(other, &book));
+        if let Some(book) = merge_duplicate(duplicate, book) {
+            deduped.push(book);
+        }
+    }
+    deduped
+}
+
+/// Combines the scans of two libraries, like a local disk and a NAS.
+///
+/// A book of `b` with the title, authors and year of a book of `a` is merged into it, tracks
+/// present in both are kept once. Unlike [`dedupe_books`] the books of each library are left
+/// alone, only matches across the two are merged. Books that can't be merged are kept side
+/// by side, the books of `a` come first.
+pub fn merge_libraries(a: Vec<Book>, b: Vec<Book>) -> Vec<Book> {
+    let mut merged = a;
+    let from_a = merged.len();
+    for book in b {
+        let duplicate = merged[..from_a]
+            .iter_mut()
+            .find(|other| same_book(other, &book));
+        if let Some(book) = merge_duplicate(duplicate, book) {
+            merged.push(book);
+        }
+    }
+    merged
+}
+
+/// Whether two books are copies of the same one, see [`dedupe_books`].
+fn same_book(lhs: &Book, rhs: &Book) -> bool {
+    lhs.title == rhs.title && lhs.author == rhs.author && lhs.year == rhs.year
+}
+
+/// Merges `book` into its duplicate, returns it if there's none or they can't be merged.
+fn merge_duplicate(duplicate: Option<&mut Book>, book: Book) -> Option<Book> {
+    let Some(duplicate) = duplicate else {
+        return Some(book);
+    };
+    // merging drops the chapters, but copies of one file share theirs
+    let chapters = (duplicate.chapters == book.chapters).then(|| book.chapters.clone());
+    match Book::merge(duplicate.clone(), book.clone()) {
+        Ok(mut merged) => {
+            merged.remove_duplicate_tracks();
+            if let Some(chapters) = chapters {
+                merged.chapters = chapters;
+            }
+            *duplicate = merged;
+            None
+        }
+        Err(e) => {
+            warn!("Can't merge duplicate book {:?}: {}", book.title, e);
+            Some(book)
+        }
+    }
+}
+
+/// Merges books split across sibling folders like `Dracula (Disc 1)` and `Dracula (Disc 2)`.
+///
+/// Books are grouped by authors and their title without a `(Disc N)`, `[CD N]`, `- Disc N`
+/// or `CD N` suffix. The suffix becomes the disc of tracks that don't know theirs, so a book
+/// of such parts has as many discs as the highest part number. Books without a suffix and
+/// parts that can't be merged are kept as they are.
+pub fn group_split_books(books: Vec<Book>) -> Vec<Book> {
+    let mut grouped: Vec<Book> = Vec::with_capacity(books.len());
+    let mut parts: Vec<Vec<(u32, Book)>> = Vec::new();
+    for book in books {
+        let Some((title, disc)) = split_disc_suffix(&book.title) else {
+            grouped.push(book);
+            continue;
+        };
+        let title = title.to_string();
+        match parts.iter_mut().find(|group| {
+            let other = &group[0].1;
+            split_disc_suffix(&other.title).is_some_and(|(other, _)| other == title)
+                && other.author == book.author
+        }) {
+            Some(group) => group.push((disc, book)),
+            None => parts.push(vec![(disc, book)]),
+        }
+    }
+
+    for mut group in parts {
+        group.sort_by_key(|(disc, _)| *disc);
+        let discs = group
+            .iter()
+            .map(|(disc, book)| book.discs.unwrap_or(0).max(*disc))
+            .max();
+        // the original of each part is kept in case it can't be merged
+        let mut group = group.into_iter().map(|(disc, original)| {
+            let mut book = original.clone();
+            if let Some((title, _)) = split_disc_suffix(&book.title) {
+                book.title = title.to_string();
+            }
+            for track in &mut book.tracks {
+                track.disc.get_or_insert(disc);
+            }
+            book.discs = discs;
+            book.votes.discs.clear();
+            (original, book)
+        });
+        let Some((first, mut merged)) = group.next() else {
+            continue;
+        };
+        let mut merged_any = false;
+        for (original, book) in group {
+            match Book::merge(merged.clone(), book) {
+                Ok(book) => {
+                    merged = book;
+                    merged_any = true;
+                }
+                Err(e) => {
+                    warn!("Can't merge split book {:?}: {}", original.title, e);
+                    grouped.push(original);
+                }
+            }
+        }
+        if merged_any {
+            merged.tracks.sort_by_key(Track::position);
+            grouped.push(merged);
+        } else {
+            grouped.push(first);
+        }
+    }
+    grouped
+}
+
+/// The books with an author matching `name`, see [`Book::has_author`].
+pub fn filter_by_author<'a>(
+    books: impl IntoIterator<Item = &'a Book> + 'a,
+    name: &'a str,
+    matching: NameMatch,
+) -> impl Iterator<Item = &'a Book> + 'a {
+    books
+        .into_iter()
+        .filter(move |book| book.has_author(name, matching))
+}
+
+/// Groups books by author, a book with several authors is listed under each of them.
+///
+/// Books without author are listed under an empty name, the books of an author keep their
+/// order.
+pub fn group_by_author(books: Vec<Book>) -> BTreeMap<String, Vec<Book>> {
+    let mut grouped: BTreeMap<String, Vec<Book>> = BTreeMap::new();
+    for book in books {
+        if book.author.is_empty() {
+            grouped.entry(String::new()).or_default().push(book);
+            continue;
+        }
+        for author in &book.author {
+            grouped
+                .entry(author.clone())
+                .or_default()
+                .push(book.clone());
+        }
+    }
+    grouped
+}
+
+/// Splits a `(Disc N)`, `[CD N]`, `- Disc N` or `CD N` suffix off a title, ignoring case.
+fn split_disc_suffix(title: &str) -> Option<(&str, u32)> {
+    let trimmed = title.trim_end();
+    let (rest, closing) = match trimmed.strip_suffix(')').or(trimmed.strip_suffix(']')) {
+        Some(rest) => (rest, true),
+        None => (trimmed, false),
+    };
+    let digits = rest.len() - rest.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let disc = rest[rest.len() - digits..].parse().ok()?;
+    let rest = rest[..rest.len() - digits].trim_end();
+    let lower = rest.to_ascii_lowercase();
+    let word = ["disc", "disk", "cd"]
+        .iter()
+        .find(|word| lower.ends_with(*word))?;
+    // the word must stand on its own, `Abracadabra 2` isn't a part
+    let rest = &rest[..rest.len() - word.len()];
+    if !rest.ends_with(|c: char| c.is_whitespace() || c == '(' || c == '[' || c == '-') {
+        return None;
+    }
+    let rest = rest.trim_end();
+    let rest = if closing {
+        rest.strip_suffix('(').or(rest.strip_suffix('['))?
+    } else {
+        rest.strip_suffix('-').unwrap_or(rest)
+    };
+    let title = rest.trim_end();
+    if title.is_empty() {
+        return None;
+    }
+    Some((title, disc))
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::book::Fnv1a;
+
+    /// Copies the files of a TestData directory into `to`.
+    pub(crate) fn copy_book(from: &str, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(Path::new("../TestData").join(from)).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_file() {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    /// Copies a TestData file into `to` with its ID3 tag changed by `edit`, so the fixtures
+    /// stay as they were downloaded.
+    fn copy_retagged(from: &str, to: &Path, edit: impl FnOnce(&mut Tag)) -> PathBuf {
+        let path = to.join(Path::new(from).file_name().unwrap());
+        std::fs::copy(Path::new("../TestData").join(from), &path).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        edit(&mut tag);
+        tag.write_to_path(&path, tag.version()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_single_file() {
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        let track = &book.tracks[0];
+        insta::assert_yaml_snapshot!(track);
+
+        let book = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let track = &book.tracks[0];
+        insta::assert_yaml_snapshot!(track);
+
+        let book =
+            parse_file("../TestData/Penguin Island/penguin_island_01_france_64kb.mp3").unwrap();
+        let track = &book.tracks[0];
+        insta::assert_yaml_snapshot!(track);
+
+        let book = parse_file("../TestData/Winnetou/winnetou1_01_may_64kb.mp3").unwrap();
+        let track = &book.tracks[0];
+        insta::assert_yaml_snapshot!(track);
+    }
+
+    #[test]
+    fn test_stream_info() {
+        // constant bitrate mp3s, one of 64 and one of 32 kbps
+        let book = parse_book("../TestData/The Moonstone").unwrap().unwrap();
+        let bitrates: Vec<_> = book.tracks.iter().map(|t| t.bitrate_kbps).collect();
+        assert_eq!(bitrates, vec![Some(64), Some(32)]);
+        assert!(book.tracks.iter().all(|t| t.sample_rate_hz == Some(22050)));
+        assert_eq!(book.min_bitrate(), Some(32));
+
+        let book =
+            parse_file("../TestData/The Invisible Man/invisible_man_d1_01_wells.flac").unwrap();
+        assert_eq!(book.tracks[0].sample_rate_hz, Some(22050));
+    }
+
+    #[test]
+    fn test_gapless() {
+        let book = parse_book("../TestData/The Moonstone").unwrap().unwrap();
+        assert_eq!(book.gapless(), Some(true));
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        assert_eq!(book.gapless(), Some(true));
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert_eq!(book.gapless(), None);
+
+        // one track without the flag makes it unknown for the book
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("The Moonstone", dir.path());
+        let path = dir.path().join("moonstone_02_collins_32kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.remove_extended_text(Some("GAPLESS"), None);
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(book.gapless(), None);
+    }
+
+    #[test]
+    fn test_book_from_tag() {
+        let mut tag = Tag::new();
+        tag.set_album("Emma (Unabridged)");
+        tag.set_album_artist("Jane Austen");
+        tag.set_title("Chapter 3");
+        tag.set_artist("Elizabeth Klett");
+        tag.set_track(3);
+        tag.set_total_tracks(55);
+        tag.set_year(2010);
+        let path = Path::new("emma_03_austen.mp3");
+        let book = book_from_tag(&tag, path).unwrap();
+        assert_eq!(book.title, "Emma (Unabridged)");
+        assert_eq!(book.author, BTreeSet::from(["Jane Austen".to_string()]));
+        assert_eq!(book.expected_tracks, Some(55));
+        assert_eq!(book.year, Some(2010));
+        assert_eq!(book.abridged, Some(false));
+        assert_eq!(book.tracks[0].track, 3);
+        assert_eq!(book.tracks[0].path, path);
+        assert_eq!(book.total_duration, None);
+
+        tag.remove_album();
+        let err = book_from_tag(&tag, path).unwrap_err();
+        assert!(matches!(err, ParseError::MissingAlbum(_)));
+    }
+
+    #[test]
+    fn test_abridged() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        assert_eq!(parse_file(&path).unwrap().abridged(), None);
+
+        // the frame wins over the title
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_album("The Adventures of Huckleberry Finn (Unabridged)");
+        tag.add_frame(id3::frame::ExtendedText {
+            description: "ABRIDGED".to_string(),
+            value: "1".to_string(),
+        });
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        assert_eq!(parse_file(&path).unwrap().abridged(), Some(true));
+
+        tag.remove_extended_text(Some("ABRIDGED"), None);
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        let book = parse_file(&path).unwrap();
+        assert_eq!(book.abridged(), Some(false));
+    }
+
+    #[test]
+    fn test_merge_abridged() {
+        let book = |title: &str, abridged: Option<bool>| {
+            let mut book =
+                parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+            book.title = title.to_string();
+            book.abridged = abridged;
+            book
+        };
+        let merged = Book::merge(book("Emma", None), book("Emma", Some(false))).unwrap();
+        assert_eq!(merged.abridged(), Some(false));
+        let err = Book::merge(book("Emma", Some(true)), book("Emma", Some(false))).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::AbridgedMismatch {
+                left: true,
+                right: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_abridged_from_title() {
+        assert_eq!(abridged_from_title("Emma (Unabridged)"), Some(false));
+        assert_eq!(abridged_from_title("Emma [ABRIDGED]"), Some(true));
+        assert_eq!(abridged_from_title("Emma"), None);
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert_eq!(parse_flag(" 1"), Some(true));
+        assert_eq!(parse_flag("FALSE"), Some(false));
+        assert_eq!(parse_flag("yes please"), None);
+    }
+
+    #[test]
+    fn test_rating() {
+        // three stars on the first track, five on the second
+        let book = parse_book("../TestData/The Moonstone").unwrap().unwrap();
+        assert_eq!(book.rating(), Some(5));
+        let book = parse_file("../TestData/The Moonstone/moonstone_01_collins_64kb.mp3").unwrap();
+        assert_eq!(book.rating(), Some(3));
+
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert_eq!(book.rating(), None);
+    }
+
+    #[test]
+    fn test_popm_stars() {
+        let stars: Vec<_> = [0, 1, 64, 128, 196, 255].map(popm_stars).to_vec();
+        assert_eq!(
+            stars,
+            vec![None, Some(1), Some(2), Some(3), Some(4), Some(5)]
+        );
+    }
+
+    #[test]
+    fn test_movement() {
+        let book = parse_book("../TestData/The Leatherstocking Tales")
+            .unwrap()
+            .unwrap();
+        let movements: Vec<_> = book
+            .tracks
+            .iter()
+            .map(|track| (track.movement(), track.movement_index()))
+            .collect();
+        assert_eq!(
+            movements,
+            vec![
+                (Some("The Deerslayer"), Some(1)),
+                (Some("The Last of the Mohicans"), Some(2))
+            ]
+        );
+
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert_eq!(book.tracks[0].movement(), None);
+        assert_eq!(book.tracks[0].movement_index(), None);
+    }
+
+    #[test]
+    fn test_unknown_text() {
+        let mut tag = Tag::new();
+        let mut add = |id: &str, data: &[u8]| {
+            tag.add_frame(id3::Frame::with_content(
+                id,
+                id3::Content::Unknown(id3::frame::Unknown {
+                    data: data.to_vec(),
+                    version: id3::Version::Id3v24,
+                }),
+            ));
+        };
+        add("MVNM", b"\x00Na\xefve\x00");
+        add("MVIN", b"\x01\xff\xfe2\x00/\x003\x00");
+        add("MVC1", b"\x09junk");
+        assert_eq!(unknown_text(&tag, "MVNM").as_deref(), Some("Na\u{ef}ve"));
+        assert_eq!(unknown_text(&tag, "MVIN").as_deref(), Some("2/3"));
+        assert_eq!(unknown_text(&tag, "MVC1"), None);
+        assert_eq!(unknown_text(&tag, "GRP1"), None);
+    }
+
+    #[test]
+    fn test_encoder() {
+        // the files were encoded by different LAME versions
+        let book = parse_book("../TestData/The Moonstone").unwrap().unwrap();
+        let encoders: Vec<_> = book.tracks.iter().map(Track::encoder).collect();
+        assert_eq!(encoders, vec![Some("LAME 3.100"), Some("LAME 3.99.5")]);
+
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        assert_eq!(book.tracks[0].encoder(), Some("Audible ACM"));
+
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert_eq!(book.tracks[0].encoder(), None);
+    }
+
+    #[test]
+    fn test_id3v1_fallback() {
+        // the title is only in the ID3v1 tag, the album in both
+        let book = parse_file("../TestData/Dracula/dracula_01_stoker_64kb.mp3").unwrap();
+        assert_eq!(book.tracks[0].title, "Jonathan Harker's Journal");
+        assert_eq!(book.title, "Dracula");
+        assert_eq!(book.year, Some(1897));
+        assert!(book.author.contains("Bram Stoker"));
+    }
+
+    #[test]
+    fn test_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = copy_retagged("sherlock_holmes.mp3", dir.path(), |tag| {
+            tag.add_frame(id3::frame::Lyrics {
+                lang: "eng".to_string(),
+                description: String::new(),
+                text: "The Red-Headed League. I had called upon my friend, Mr. Sherlock Holmes, \
+                       one day in the autumn of last year."
+                    .to_string(),
+            });
+        });
+        let book = parse_file(path).unwrap();
+        let transcript = book.tracks[0].transcript().unwrap();
+        assert!(transcript.starts_with("The Red-Headed League. I had called upon my friend"));
+        // only serialized if there is one
+        assert!(book.to_json().unwrap().contains("\"transcript\""));
+
+        let book = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        assert_eq!(book.tracks[0].transcript(), None);
+        assert!(!book.to_json().unwrap().contains("transcript"));
+    }
+
+    #[test]
+    fn test_artist_as_author() {
+        // tagged without album artist
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert!(book.author.is_empty());
+
+        let options = ParseOptions {
+            artist_as_author: true,
+            ..Default::default()
+        };
+        let book = parse_file_with_options("../TestData/sherlock_holmes.mp3", &options).unwrap();
+        assert_eq!(
+            book.author,
+            BTreeSet::from(["Sir Arthur Conan Doyle".to_string()])
+        );
+        // the album artist wins if there is one
+        let book = parse_file_with_options(
+            "../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(book.author, BTreeSet::from(["Mark Twain".to_string()]));
+    }
+
+    #[test]
+    fn test_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = copy_retagged("sherlock_holmes.mp3", dir.path(), |tag| {
+            tag.set_text("TLAN", "eng");
+        });
+        let book = parse_file(path).unwrap();
+        insta::assert_yaml_snapshot!(book);
+        assert_eq!(book.language.as_deref(), Some("eng"));
+
+        // untagged books don't get a guessed language
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert_eq!(book.language, None);
+
+        for value in ["eng", "en", "English", " EN ", "eng/ger"] {
+            assert_eq!(
+                normalize_language(value).as_deref(),
+                Some("eng"),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(normalize_language("deu").as_deref(), Some("ger"));
+        assert_eq!(normalize_language("tlh").as_deref(), Some("tlh"));
+        assert_eq!(normalize_language(""), None);
+    }
+
+    #[test]
+    fn test_merge_language_mismatch() {
+        let lhs = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let mut rhs = parse_file("../TestData/Huckfinn/huckfinn_02_twain_apc_64kb.mp3").unwrap();
+        rhs.language = Some("eng".to_string());
+
+        let err = Book::merge_with(lhs, rhs, MergeStrategy::PreferFirst).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::LanguageMismatch { right: Some(_), .. }
+        ));
+    }
+
+    #[test]
+    fn test_description() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Penguin Island", dir.path());
+        copy_retagged(
+            "Penguin Island/penguin_island_01_france_64kb.mp3",
+            dir.path(),
+            |tag| {
+                tag.add_frame(id3::frame::Comment {
+                    lang: "eng".to_string(),
+                    description: String::new(),
+                    text: "A satirical history of France, told as the chronicle of an island of \
+                           penguins baptised by a near-sighted missionary."
+                        .to_string(),
+                });
+            },
+        );
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        insta::assert_yaml_snapshot!(book);
+        assert!(book
+            .description
+            .as_deref()
+            .is_some_and(|description| description.starts_with("A satirical history")));
+
+        // the longest description wins
+        let mut lhs = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let mut rhs = parse_file("../TestData/Huckfinn/huckfinn_02_twain_apc_64kb.mp3").unwrap();
+        lhs.description = Some("Huck".to_string());
+        rhs.description = Some("Huck and Jim".to_string());
+        let book = Book::merge(lhs, rhs).unwrap();
+        assert_eq!(book.description.as_deref(), Some("Huck and Jim"));
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = copy_retagged("sherlock_holmes.mp3", dir.path(), |tag| {
+            for (description, value) in [("ISBN", "978-0-14-043771-3"), ("ASIN", "B000SEGS5A")] {
+                tag.add_frame(id3::frame::ExtendedText {
+                    description: description.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        });
+        let book = parse_file(path).unwrap();
+        assert_eq!(book.isbn.as_deref(), Some("9780140437713"));
+        assert_eq!(book.asin.as_deref(), Some("B000SEGS5A"));
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        assert_eq!(book.isbn.as_deref(), Some("0486264726"));
+        assert_eq!(book.asin, None);
+
+        let lhs = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let mut rhs = parse_file("../TestData/Huckfinn/huckfinn_02_twain_apc_64kb.mp3").unwrap();
+        rhs.isbn = Some("9780140437713".to_string());
+        let err = Book::merge(lhs, rhs).unwrap_err();
+        assert!(matches!(err, ParseError::IsbnMismatch { left: None, .. }));
+    }
+
+    #[test]
+    fn test_isbn_checksum() {
+        assert!(is_valid_isbn("9780140437713"));
+        assert!(is_valid_isbn("0486264726"));
+        assert!(is_valid_isbn("080442957X"));
+        assert!(!is_valid_isbn("9780140437714"));
+        assert!(!is_valid_isbn("0486264727"));
+        assert!(!is_valid_isbn("X486264726"));
+        assert!(!is_valid_isbn("978014043771"));
+        assert!(!is_valid_isbn(""));
+        assert_eq!(
+            normalize_isbn(Path::new("book.mp3"), "978-0-14-043771-3").as_deref(),
+            Some("9780140437713")
+        );
+        assert_eq!(
+            normalize_isbn(Path::new("book.mp3"), "978-0-14-043771-4"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_publisher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = copy_retagged("sherlock_holmes.mp3", dir.path(), |tag| {
+            tag.set_text("TPUB", "LibriVox");
+        });
+        let book = parse_file(path).unwrap();
+        assert_eq!(book.publisher.as_deref(), Some("LibriVox"));
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        assert_eq!(book.publisher.as_deref(), Some("LibriVox"));
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert_eq!(book.publisher, None);
+
+        let lhs = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let mut rhs = parse_file("../TestData/Huckfinn/huckfinn_02_twain_apc_64kb.mp3").unwrap();
+        rhs.publisher = Some("LibriVox".to_string());
+        let err = Book::merge(lhs, rhs).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::PublisherMismatch { left: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_m4b_file() {
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        insta::assert_yaml_snapshot!(book);
+    }
+
+    #[test]
+    fn test_mp4_chapters() {
+        let book =
+            parse_file("../TestData/The Call of the Wild/call_of_the_wild_london.m4b").unwrap();
+        assert_eq!(book.chapters.len(), 3);
+        assert_eq!(book.chapters[0].title, "Into the Primitive");
+        assert_eq!(book.chapters[0].start, Duration::ZERO);
+        assert_eq!(book.chapters[0].end, Duration::from_secs(10));
+        assert_eq!(book.chapters[2].end, book.total_duration.unwrap());
+    }
+
+    #[test]
+    fn test_id3_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        for (idx, title) in ["Civilizing Huck", "Our Gang's Dark Oath"]
+            .iter()
+            .enumerate()
+        {
+            let start = idx as u32 * 10_000;
+            tag.add_frame(id3::frame::Chapter {
+                element_id: format!("chp{}", idx),
+                start_time: start,
+                end_time: start + 10_000,
+                start_offset: u32::MAX,
+                end_offset: u32::MAX,
+                frames: vec![id3::Frame::text("TIT2", *title)],
+            });
+        }
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let book = parse_file(&path).unwrap();
+        assert_eq!(book.chapters.len(), 2);
+        assert_eq!(book.chapters[1].title, "Our Gang's Dark Oath");
+        assert_eq!(book.chapters[1].start, Duration::from_secs(10));
+
+        // the chapters of a single file are meaningless for the whole book
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert!(book.chapters.is_empty());
+    }
+
+    #[test]
+    fn test_parse_flac_file() {
+        let book = parse_file("../TestData/The Time Machine/time_machine_01_wells.flac").unwrap();
+        insta::assert_yaml_snapshot!(book);
+    }
+
+    #[test]
+    fn test_parse_ogg_file() {
+        let book =
+            parse_file("../TestData/Treasure Island/treasure_island_01_stevenson.ogg").unwrap();
+        insta::assert_yaml_snapshot!(book);
+    }
+
+    #[test]
+    fn test_parse_mka_file() {
+        let book =
+            parse_file("../TestData/Heart of Darkness/heart_of_darkness_conrad.mka").unwrap();
+        insta::assert_yaml_snapshot!(book);
+        // the hidden credits chapter is left out
+        let titles: Vec<&str> = book.chapters.iter().map(Chapter::title).collect();
+        assert_eq!(titles, vec!["Part 1", "Part 2", "Part 3"]);
+        assert!(book.author.contains("Joseph Conrad"));
+        assert!(book.reader().contains("Tony Foster"));
+    }
+
+    #[test]
+    fn test_parse_opus_file() {
+        let book = parse_file("../TestData/Metamorphosis/metamorphosis_01_kafka.opus").unwrap();
+        insta::assert_yaml_snapshot!(book);
+        assert_eq!(book.tracks[0].track, 1);
+        assert_eq!(book.expected_tracks, Some(2));
+
+        let book = parse_book("../TestData/Metamorphosis").unwrap().unwrap();
+        assert_eq!(book.total_duration, Some(Duration::from_secs(10)));
+        assert!(book.is_complete());
+    }
+
+    #[test]
+    fn test_series() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Winnetou", dir.path());
+        let path = dir.path().join("winnetou1_01_may_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        for (description, value) in [("SERIES", "Winnetou"), ("SERIES-PART", "1")] {
+            tag.add_frame(id3::frame::ExtendedText {
+                description: description.to_string(),
+                value: value.to_string(),
+            });
+        }
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        let book = parse_file(&path).unwrap();
+        insta::assert_yaml_snapshot!(book);
+
+        let path = dir.path().join("call_of_the_wild_london.m4b");
+        std::fs::copy(
+            "../TestData/The Call of the Wild/call_of_the_wild_london.m4b",
+            &path,
+        )
+        .unwrap();
+        let mut file = Mp4File::read_from(
+            &mut File::open(&path).unwrap(),
+            lofty::config::ParseOptions::new(),
+        )
+        .unwrap();
+        let ilst = file.ilst_mut().unwrap();
+        for (name, value) in [("SERIES", "Yukon"), ("SERIES-PART", "1.5")] {
+            ilst.insert(lofty::mp4::Atom::new(
+                AtomIdent::Freeform {
+                    mean: "com.apple.iTunes".into(),
+                    name: name.into(),
+                },
+                AtomData::UTF8(value.to_string()),
+            ));
+        }
+        file.save_to_path(&path, lofty::config::WriteOptions::default())
+            .unwrap();
+        let book = parse_file(&path).unwrap();
+        assert_eq!(book.series.as_deref(), Some("Yukon"));
+        assert_eq!(book.series_index, Some(1.5));
+    }
+
+    #[test]
+    fn test_parse_book() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        insta::assert_yaml_snapshot!(book);
+    }
+
+    #[test]
+    fn test_multi_disc_order() {
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        let order: Vec<(Option<u32>, u32)> = book
+            .tracks
+            .iter()
+            .map(|track| (track.disc, track.track))
+            .collect();
+        assert_eq!(
+            order,
+            vec![(Some(1), 1), (Some(1), 2), (Some(2), 1), (Some(2), 2)]
+        );
+        assert_eq!(book.tracks[2].title, "The Thousand and One Bottles");
+    }
+
+    #[test]
+    fn test_disc_subtitles() {
+        // every disc has two tracks naming it with TSST
+        let book = parse_book("../TestData/Twenty Thousand Leagues Under the Sea")
+            .unwrap()
+            .unwrap();
+        insta::assert_yaml_snapshot!(book);
+        assert_eq!(
+            book.disc_subtitles,
+            vec![(1, "Part One".to_string()), (2, "Part Two".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_track_paths() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        for track in &book.tracks {
+            let expected = format!(
+                "../TestData/Huckfinn/huckfinn_{:02}_twain_apc_64kb.mp3",
+                track.track
+            );
+            assert_eq!(track.path, Path::new(&expected));
+        }
+    }
+
+    #[test]
+    fn test_parse_book_from_files() {
+        let dir = Path::new("../TestData/Huckfinn");
+        let files = [3, 1, 2].map(|nr| dir.join(format!("huckfinn_0{}_twain_apc_64kb.mp3", nr)));
+
+        let book = parse_book_from_files(files).unwrap().unwrap();
+        assert_eq!(book.title, "The Adventures of Huckleberry Finn");
+        assert_eq!(book.total_tracks, 3);
+        let tracks: Vec<u32> = book.tracks.iter().map(|track| track.track).collect();
+        assert_eq!(tracks, vec![1, 2, 3]);
+
+        assert!(parse_book_from_files(Vec::new()).is_none());
+        // files of two books can't be merged
+        let mixed = [
+            dir.join("huckfinn_01_twain_apc_64kb.mp3"),
+            PathBuf::from("../TestData/Winnetou/winnetou1_01_may_64kb.mp3"),
+        ];
+        assert!(parse_book_from_files(mixed).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_parse_book_verbose() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        std::fs::write(dir.path().join("corrupt.mp3"), b"not an mp3 file").unwrap();
+
+        let (book, skipped) = parse_book_verbose(dir.path()).unwrap().unwrap();
+        assert_eq!(book.tracks.len(), 5);
+        assert_eq!(skipped.len(), 1);
+        assert!(
+            matches!(&skipped[0], ParseError::Unreadable { path, .. } if path.ends_with("corrupt.mp3"))
+        );
+
+        // parse_book only logs the skipped file
+        assert_eq!(parse_book(dir.path()).unwrap().unwrap(), book);
+    }
+
+    #[test]
+    fn test_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mp3 = "huckfinn_01_twain_apc_64kb.mp3";
+        std::fs::copy(
+            Path::new("../TestData/Huckfinn").join(mp3),
+            dir.path().join(mp3),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("cover.jpg"), b"not really a jpeg").unwrap();
+
+        // the image isn't even tried, so there's nothing to warn about
+        let (book, skipped) = parse_book_verbose(dir.path()).unwrap().unwrap();
+        assert_eq!(book.tracks.len(), 1);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+
+        let options = ParseOptions {
+            extensions: vec!["flac".to_string()],
+            ..Default::default()
+        };
+        assert!(parse_book_with_options(dir.path(), &options)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_skip_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let mp3 = "huckfinn_01_twain_apc_64kb.mp3";
+        std::fs::copy(
+            Path::new("../TestData/Huckfinn").join(mp3),
+            dir.path().join(mp3),
+        )
+        .unwrap();
+        // the resource fork macOS writes next to the track on a network share
+        std::fs::write(dir.path().join(format!("._{}", mp3)), b"\0\x05\x16\x07").unwrap();
+
+        let (book, skipped) = parse_book_verbose(dir.path()).unwrap().unwrap();
+        assert_eq!(book.tracks.len(), 1);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+
+        let options = ParseOptions {
+            skip_hidden: false,
+            ..Default::default()
+        };
+        let (book, skipped) = parse_book_verbose_with_options(dir.path(), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.tracks.len(), 1);
+        assert_eq!(skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_books_in_dir() {
+        let options = ParseOptions {
+            split_mixed_dirs: true,
+            ..Default::default()
+        };
+        let books = parse_books_in_dir("../TestData/Twain Collection", &options).unwrap();
+        let titles: Vec<(&str, usize)> = books
+            .iter()
+            .map(|book| (book.title.as_str(), book.tracks.len()))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("A Connecticut Yankee in King Arthur's Court", 2),
+                ("The Prince and the Pauper", 2)
+            ]
+        );
+
+        // without the flag the titles conflict
+        let err = parse_books_in_dir("../TestData/Twain Collection", &ParseOptions::default())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ParseError::TitleMismatch { .. })
+        ));
+        let books = parse_books_in_dir("../TestData/Huckfinn", &options).unwrap();
+        assert_eq!(books.len(), 1);
+    }
+
+    #[test]
+    fn test_skip_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mp3 = "huckfinn_01_twain_apc_64kb.mp3";
+        std::fs::copy(
+            Path::new("../TestData/Huckfinn").join(mp3),
+            dir.path().join(mp3),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("huckfinn_02_twain_apc_64kb.mp3"), b"broken").unwrap();
+        let options = |on_skip| ParseOptions {
+            on_skip,
+            ..Default::default()
+        };
+
+        let book = parse_book_with_options(dir.path(), &options(SkipPolicy::Warn)).unwrap();
+        assert_eq!(book.unwrap().tracks.len(), 1);
+
+        let book = parse_book_with_options(dir.path(), &options(SkipPolicy::Ignore)).unwrap();
+        assert_eq!(book.unwrap().tracks.len(), 1);
+
+        let err = parse_book_with_options(dir.path(), &options(SkipPolicy::Error)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ParseError::Unreadable { path, .. }) if path.ends_with("huckfinn_02_twain_apc_64kb.mp3")
+        ));
+    }
+
+    #[test]
+    fn test_total_duration() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert_eq!(book.total_duration, Some(Duration::from_millis(136_620)));
+    }
+
+    #[test]
+    fn test_merge_genre_mismatch() {
+        let lhs = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let mut rhs = parse_file("../TestData/Huckfinn/huckfinn_02_twain_apc_64kb.mp3").unwrap();
+        rhs.genre = Some("Spoken".to_string());
+
+        let err = Book::merge(lhs, rhs).unwrap_err();
+        assert!(matches!(
+            &err,
+            ParseError::GenreMismatch { left: Some(left), right: Some(right), .. }
+                if left == "Audiobook" && right == "Spoken"
+        ));
+        let err = err.to_string();
+        assert!(err.contains("Audiobook"), "{}", err);
+        assert!(err.contains("Spoken"), "{}", err);
+    }
+
+    /// Three parts of Huckfinn tagged with the years 1884, 1885 and 1885.
+    fn parts_with_mismatched_years() -> Vec<Book> {
+        [1884, 1885, 1885]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, year)| {
+                let path = format!(
+                    "../TestData/Huckfinn/huckfinn_0{}_twain_apc_64kb.mp3",
+                    idx + 1
+                );
+                let mut book = parse_file(path).unwrap();
+                book.year = Some(year);
+                book
+            })
+            .collect()
+    }
+
+    fn merge_parts(strategy: MergeStrategy) -> Result<Book, ParseError> {
+        let mut parts = parts_with_mismatched_years().into_iter();
+        let first = parts.next().unwrap();
+        parts.try_fold(first, |lhs, rhs| Book::merge_with(lhs, rhs, strategy))
+    }
+
+    #[test]
+    fn test_merge_strict() {
+        let err = merge_parts(MergeStrategy::Strict).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::YearMismatch {
+                left: Some(1884),
+                right: Some(1885),
+                ..
+            }
+        ));
+        assert_eq!(MergeStrategy::default(), MergeStrategy::Strict);
+    }
+
+    #[test]
+    fn test_merge_prefer_first() {
+        let book = merge_parts(MergeStrategy::PreferFirst).unwrap();
+        assert_eq!(book.year, Some(1884));
+        assert_eq!(book.tracks.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_prefer_most_common() {
+        let book = merge_parts(MergeStrategy::PreferMostCommon).unwrap();
+        assert_eq!(book.year, Some(1885));
+        assert_eq!(book.tracks.len(), 3);
+
+        // the order of the parts doesn't matter
+        let mut parts = parts_with_mismatched_years();
+        parts.rotate_left(1);
+        let book = parts
+            .into_iter()
+            .reduce(|lhs, rhs| Book::merge_with(lhs, rhs, MergeStrategy::PreferMostCommon).unwrap())
+            .unwrap();
+        assert_eq!(book.year, Some(1885));
+
+        // the votes don't count for equality
+        let mut unmerged = book.clone();
+        unmerged.votes = Default::default();
+        assert_eq!(unmerged, book);
+    }
+
+    #[test]
+    fn test_parse_book_merge_strategy() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        for (nr, year) in [(1, 1884), (2, 1885), (3, 1885), (4, 1885), (5, 1884)] {
+            let path = dir
+                .path()
+                .join(format!("huckfinn_0{}_twain_apc_64kb.mp3", nr));
+            let mut tag = Tag::read_from_path(&path).unwrap();
+            tag.set_year(year);
+            tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        }
+
+        assert!(parse_book(dir.path()).is_err());
+
+        let options = ParseOptions {
+            merge_strategy: MergeStrategy::PreferMostCommon,
+            ..Default::default()
+        };
+        let book = parse_book_with_options(dir.path(), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.year, Some(1885));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_file("../TestData/missing.mp3").unwrap_err();
+        assert!(matches!(err, ParseError::Unreadable { .. }));
+
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        // the ID3v1 tag would fill in what's removed
+        id3::v1::Tag::remove_from_path(&path).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.remove_track();
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        let err = parse_file(&path).unwrap_err();
+        assert!(matches!(err, ParseError::MissingTrackNumber(p) if p == path));
+
+        tag.remove_title();
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        let err = parse_file(&path).unwrap_err();
+        assert!(matches!(err, ParseError::MissingTitle(p) if p == path));
+
+        let huckfinn = parse_file("../TestData/Huckfinn/huckfinn_01_twain_apc_64kb.mp3").unwrap();
+        let penguin =
+            parse_file("../TestData/Penguin Island/penguin_island_01_france_64kb.mp3").unwrap();
+        let err = Book::merge(huckfinn, penguin).unwrap_err();
+        assert!(matches!(err, ParseError::TitleMismatch { .. }));
+    }
+
+    #[test]
+    fn test_filename_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let book_dir = dir.path().join("Huckfinn");
+        copy_book("Huckfinn", &book_dir);
+        let path = book_dir.join("huckfinn_01_twain_apc_64kb.mp3");
+        id3::v1::Tag::remove_from_path(&path).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.remove_title();
+        tag.remove_album();
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        assert!(parse_file(&path).is_err());
+
+        let options = ParseOptions {
+            filename_fallback: true,
+            ..Default::default()
+        };
+        let book = parse_file_with_options(&path, &options).unwrap();
+        assert_eq!(book.title, "Huckfinn");
+        assert_eq!(book.tracks[0].title, "huckfinn 01");
+    }
+
+    #[test]
+    fn test_multiple_album_artists() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_album_artist("Mark Twain/Charles Dickens");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let book = parse_file(&path).unwrap();
+        assert_eq!(
+            book.author,
+            BTreeSet::from(["Charles Dickens".to_string(), "Mark Twain".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_reader_field() {
+        // tagged the Audible way, the artist is the author and the composer the narrator
+        let path = Path::new("../TestData/The War of the Worlds/war_of_the_worlds_01.m4b");
+        let book = parse_file(path).unwrap();
+        assert_eq!(book.reader(), BTreeSet::from(["H. G. Wells".to_string()]));
+
+        let options = ParseOptions {
+            reader_field: ReaderField::Composer,
+            ..Default::default()
+        };
+        let book = parse_file_with_options(path, &options).unwrap();
+        assert_eq!(book.reader(), BTreeSet::from(["Rebecca Doerr".to_string()]));
+        assert_eq!(book.tracks[0].reader, vec!["Rebecca Doerr"]);
+
+        let options = ParseOptions {
+            reader_field: ReaderField::Narrator,
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_file_with_options(path, &options),
+            Err(ParseError::MissingArtist(_))
+        ));
+    }
+
+    #[test]
+    fn test_reader_field_id3() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.add_frame(id3::frame::ExtendedText {
+            description: "NARRATOR".to_string(),
+            value: "Patrick Fraley".to_string(),
+        });
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let options = ParseOptions {
+            reader_field: ReaderField::Narrator,
+            ..Default::default()
+        };
+        let book = parse_file_with_options(&path, &options).unwrap();
+        assert_eq!(
+            book.reader(),
+            BTreeSet::from(["Patrick Fraley".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_original_year() {
+        // recorded for LibriVox in 2006 (TDRC), first published in 1884 (TDOR)
+        let dir = tempfile::tempdir().unwrap();
+        for nr in 1..=5 {
+            let file = format!("Huckfinn/huckfinn_0{}_twain_apc_64kb.mp3", nr);
+            copy_retagged(&file, dir.path(), |tag| {
+                tag.set_text("TDRC", "2006");
+                tag.set_text("TDOR", "1884");
+            });
+        }
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(book.year, Some(2006));
+        assert_eq!(book.original_year, Some(1884));
+
+        let book =
+            parse_file("../TestData/The Invisible Man/invisible_man_d1_01_wells.flac").unwrap();
+        assert_eq!(book.year, Some(1897));
+        assert_eq!(book.original_year, None);
+    }
+
+    #[test]
+    fn test_replaygain() {
+        let book = parse_book("../TestData/The Jungle Book").unwrap().unwrap();
+        insta::assert_yaml_snapshot!(book);
+        assert_eq!(book.replaygain_album, Some(-6.48));
+    }
+
+    #[test]
+    fn test_parse_gain() {
+        assert_eq!(parse_gain("-6.48 dB"), Some(-6.48));
+        assert_eq!(parse_gain(" +2.10 DB "), Some(2.1));
+        assert_eq!(parse_gain("-1.5dB"), Some(-1.5));
+        assert_eq!(parse_gain("0"), Some(0.0));
+        assert_eq!(parse_gain("loud"), None);
+    }
+
+    #[test]
+    fn test_blank_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_01_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_title("   ");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        assert!(matches!(
+            parse_file(&path),
+            Err(ParseError::MissingTitle(_))
+        ));
+        let options = ParseOptions {
+            filename_fallback: true,
+            ..Default::default()
+        };
+        let book = parse_file_with_options(&path, &options).unwrap();
+        assert_eq!(book.tracks[0].title, "huckfinn 01");
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_title("Chapter 1");
+        tag.set_album("");
+        tag.set_artist(" / ");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        assert!(matches!(
+            parse_file(&path),
+            Err(ParseError::MissingArtist(_))
+        ));
+        assert!(matches!(
+            parse_file_with_options(&path, &options),
+            Err(ParseError::MissingArtist(_))
+        ));
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_artist("John Greenman");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        assert!(matches!(
+            parse_file(&path),
+            Err(ParseError::MissingAlbum(_))
+        ));
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let options = ParseOptions {
+            io_retries: 2,
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        };
+        // a network share timing out `failures` times before the read works
+        let flaky_read = |failures: u32, kind: std::io::ErrorKind| {
+            let mut calls = 0;
+            move || {
+                calls += 1;
+                if calls <= failures {
+                    Err(ParseError::unreadable(
+                        "/mnt/share/01.mp3",
+                        std::io::Error::from(kind),
+                    ))
+                } else {
+                    Ok(calls)
+                }
+            }
+        };
+
+        assert_eq!(
+            with_retries(&options, flaky_read(2, std::io::ErrorKind::TimedOut)).unwrap(),
+            3
+        );
+        assert!(with_retries(&options, flaky_read(3, std::io::ErrorKind::TimedOut)).is_err());
+        assert_eq!(
+            with_retries(&options, flaky_read(1, std::io::ErrorKind::Interrupted)).unwrap(),
+            2
+        );
+
+        // a broken file stays broken, it isn't read again
+        let mut calls = 0;
+        let res = with_retries(&options, || {
+            calls += 1;
+            Err::<(), _>(ParseError::unreadable(
+                "/mnt/share/01.mp3",
+                std::io::Error::from(std::io::ErrorKind::InvalidData),
+            ))
+        });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let id3 = id3::Error::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert!(ParseError::unreadable("01.mp3", id3).is_transient());
+        let id3 = id3::Error::new(id3::ErrorKind::NoTag, "no tag");
+        assert!(!ParseError::unreadable("01.mp3", id3).is_transient());
+        assert!(!ParseError::MissingTitle(PathBuf::from("01.mp3")).is_transient());
+    }
+
+    #[test]
+    fn test_duplicate_tracks() {
+        // two files of Winnetou are tagged as track 1
+        let book = parse_book("../TestData/Winnetou").unwrap().unwrap();
+        assert_eq!(book.duplicate_tracks(), vec![1]);
+
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert!(book.duplicate_tracks().is_empty());
+
+        // the same track number on another disc is fine
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        assert!(book.duplicate_tracks().is_empty());
+    }
+
+    #[test]
+    fn test_case_insensitive_names() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_03_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_album_artist("mark twain ");
+        tag.set_artist("annie coleman rothenberg");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(book.author.len(), 2);
+
+        let options = ParseOptions {
+            case_insensitive_names: true,
+            ..Default::default()
+        };
+        let book = parse_book_with_options(dir.path(), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.author, BTreeSet::from(["Mark Twain".to_string()]));
+        assert_eq!(
+            book.reader(),
+            BTreeSet::from(["Annie Coleman Rothenberg".to_string()])
+        );
+        assert_eq!(book.tracks[2].reader, vec!["Annie Coleman Rothenberg"]);
+    }
+
+    #[test]
+    fn test_missing_tracks() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        std::fs::remove_file(dir.path().join("huckfinn_03_twain_apc_64kb.mp3")).unwrap();
+
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(book.missing_tracks(), vec![3]);
+
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert!(book.missing_tracks().is_empty());
+
+        // the numbering restarts on the second disc
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        assert!(book.missing_tracks().is_empty());
+    }
+
+    #[test]
+    fn test_missing_discs() {
+        let book = parse_book("../TestData/Moby Dick").unwrap().unwrap();
+        assert_eq!(book.discs, Some(3));
+        assert_eq!(book.missing_discs(), vec![2]);
+        assert_eq!(book.present_discs(), 2);
+
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        assert!(book.missing_discs().is_empty());
+        assert_eq!(book.present_discs(), 2);
+        assert_eq!(book.discs, Some(2));
+        // without a total the discs can't be checked
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert_eq!(book.discs, None);
+        assert!(book.missing_discs().is_empty());
+        assert_eq!(book.present_discs(), 1);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let tag_total = |total: u32| {
+            for nr in 1..=5 {
+                let path = dir
+                    .path()
+                    .join(format!("huckfinn_0{}_twain_apc_64kb.mp3", nr));
+                let mut tag = Tag::read_from_path(&path).unwrap();
+                tag.set_total_tracks(total);
+                tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+            }
+        };
+
+        tag_total(6);
+        let book = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(book.expected_tracks, Some(6));
+        assert_eq!(book.total_tracks, 5);
+        assert!(!book.is_complete());
+        assert_eq!(book.missing_tracks(), vec![6]);
+
+        tag_total(5);
+        assert!(parse_book(dir.path()).unwrap().unwrap().is_complete());
+
+        // track 2 of 12
+        let book = parse_file("../TestData/sherlock_holmes.mp3").unwrap();
+        assert_eq!(book.expected_tracks, Some(12));
+        assert!(!book.is_complete());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        let again = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        assert_eq!(book.content_hash(), again.content_hash());
+
+        let mut shuffled = again.clone();
+        shuffled.tracks.reverse();
+        assert_eq!(book.content_hash(), shuffled.content_hash());
+
+        let mut changed = again.clone();
+        changed.year = Some(1884);
+        assert_ne!(book.content_hash(), changed.content_hash());
+
+        // a track moved to another disc is a change too
+        let mut moved = again;
+        moved.tracks[4].disc = Some(2);
+        assert_ne!(book.content_hash(), moved.content_hash());
+
+        let mut empty = Fnv1a::default();
+        empty.write(b"");
+        assert_eq!(empty.0, 0xcbf2_9ce4_8422_2325);
+        empty.write(b"a");
+        assert_eq!(empty.0, 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_cover() {
+        let book = parse_book("../TestData/Huckfinn").unwrap().unwrap();
+        let cover = book.cover.as_ref().unwrap();
+        assert_eq!(cover.mime_type, "image/jpeg");
+        assert!(!cover.data.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        book.write_cover(dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("cover.jpg")).unwrap(),
+            cover.data
+        );
+    }
+
+    #[test]
+    fn test_parse_all_books_multi() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("drive1");
+        let second = dir.path().join("drive2");
+        copy_book("Huckfinn", &first.join("Huckfinn"));
+        copy_book("Winnetou", &first.join("Winnetou"));
+        copy_book("Treasure Island", &second.join("Treasure Island"));
+
+        let books: Vec<Book> = parse_all_books_multi([first, second])
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(books.len(), 3);
+        // the roots are walked in order
+        assert_eq!(books[2].title, "Treasure Island");
+    }
+
+    #[test]
+    fn test_parse_all_books_sorted() {
+        let books = parse_all_books_sorted(PathBuf::from("../TestData")).unwrap();
+        let listing: Vec<String> = books
+            .iter()
+            .map(|book| format!("{} - {}", book.authors_joined(", "), book.title))
+            .collect();
+        insta::assert_yaml_snapshot!(listing);
+
+        assert!(parse_all_books_sorted(PathBuf::from("../TestData/Nowhere")).is_err());
+    }
+
+    #[test]
+    fn test_library_order() {
+        let book = |title: &str, series: Option<f32>| {
+            let mut builder = Book::builder()
+                .title(title)
+                .author("Karl May")
+                .track(Track::builder("Chapter 1", 1).build());
+            if let Some(index) = series {
+                builder = builder.series("Winnetou", Some(index));
+            }
+            builder.build().unwrap()
+        };
+        let mut books = [
+            book("Winnetou II", Some(2.0)),
+            book("Winnetou I", Some(1.0)),
+            book("Der Schatz im Silbersee", None),
+            Book::builder()
+                .title("Anonymous")
+                .track(Track::builder("Chapter 1", 1).build())
+                .build()
+                .unwrap(),
+        ];
+        books.sort_by(library_order);
+        let titles: Vec<&str> = books.iter().map(|book| book.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Anonymous",
+                "Der Schatz im Silbersee",
+                "Winnetou I",
+                "Winnetou II"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_par_parse_all_books() {
+        let by_title = |lhs: &Book, rhs: &Book| lhs.title.cmp(&rhs.title);
+        let mut sequential: Vec<_> = parse_all_books(PathBuf::from("../TestData"))
+            .map(Result::unwrap)
+            .collect();
+        sequential.sort_by(by_title);
+        let mut parallel: Vec<_> = par_parse_all_books(PathBuf::from("../TestData"))
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        parallel.sort_by(by_title);
+
+        assert!(!parallel.is_empty());
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn test_parse_all_books_with_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Mark Twain").join("Huckfinn"));
+        // the download of the audio failed, only the cover made it
+        let empty = dir.path().join("Mark Twain").join("Tom Sawyer");
+        std::fs::create_dir_all(&empty).unwrap();
+        std::fs::write(empty.join("cover.jpg"), b"\xff\xd8\xff").unwrap();
+        let hidden = dir.path().join("Penguin Island");
+        std::fs::create_dir_all(&hidden).unwrap();
+        std::fs::write(hidden.join(".DS_Store"), b"").unwrap();
+
+        let entries: Vec<ScanEntry> = parse_all_books_with_empty_dirs(dir.path().to_path_buf())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().any(
+            |entry| matches!(entry, ScanEntry::Book(book) if book.title == "The Adventures of Huckleberry Finn")
+        ));
+        assert!(entries.contains(&ScanEntry::EmptyBookDir(empty)));
+    }
+
+    #[test]
+    fn test_title_normalizer() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        let path = dir.path().join("huckfinn_02_twain_apc_64kb.mp3");
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_album("The Adventures of Huckleberry Finn (Unabridged)");
+        tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+        assert!(parse_book(dir.path()).is_err());
+
+        let options = ParseOptions {
+            title_normalizer: Some(normalize_title),
+            ..Default::default()
+        };
+        let book = parse_book_with_options(dir.path(), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.title, "The Adventures of Huckleberry Finn");
+        assert_eq!(book.total_tracks, 5);
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title(" Emma [UNABRIDGED]"), "Emma");
+        assert_eq!(normalize_title("Emma"), "Emma");
+        assert_eq!(normalize_title("Unabridged"), "Unabridged");
+        // lowercasing changes the length of these titles
+        assert_eq!(
+            normalize_title("\u{212A}elvin (Unabridged)"),
+            "\u{212A}elvin"
+        );
+        assert_eq!(normalize_title("İİİ (Unabridged)"), "İİİ");
+        assert_eq!(normalize_title("Ωmega[unabridged]"), "Ωmega");
+    }
+
+    #[test]
+    fn test_merge_libraries() {
+        let local = vec![
+            parse_book("../TestData/Huckfinn").unwrap().unwrap(),
+            parse_book("../TestData/Winnetou").unwrap().unwrap(),
+        ];
+        // the NAS has Huckfinn too, but only the first two tracks
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", dir.path());
+        for nr in 3..=5 {
+            let file = format!("huckfinn_{:02}_twain_apc_64kb.mp3", nr);
+            std::fs::remove_file(dir.path().join(file)).unwrap();
+        }
+        let nas = vec![
+            parse_book(dir.path()).unwrap().unwrap(),
+            parse_book("../TestData/Penguin Island").unwrap().unwrap(),
+        ];
+
+        let books = merge_libraries(local, nas);
+        let titles: Vec<&str> = books.iter().map(|book| book.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "The Adventures of Huckleberry Finn",
+                "Winnetou I",
+                "Penguin Island"
+            ]
+        );
+        assert_eq!(books[0].tracks.len(), 5);
+        assert_eq!(books[0].total_tracks, 5);
+
+        // both libraries have the chaptered m4b
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("The Call of the Wild", dir.path());
+        let local = vec![parse_book("../TestData/The Call of the Wild")
+            .unwrap()
+            .unwrap()];
+        let chapters = local[0].chapters.clone();
+        assert!(!chapters.is_empty());
+        let books = merge_libraries(local, vec![parse_book(dir.path()).unwrap().unwrap()]);
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].chapters, chapters);
+    }
+
+    #[test]
+    fn test_dedupe_books() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        std::os::unix::fs::symlink(dir.path().join("Huckfinn"), dir.path().join("latest")).unwrap();
+
+        // the symlinked directory is the same book, it's only parsed once
+        let books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books.len(), 1);
+
+        copy_book("Huckfinn", &dir.path().join("Backup").join("Huckfinn"));
+        copy_book("Penguin Island", &dir.path().join("Penguin Island"));
+        let books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books.len(), 3);
+
+        let books = dedupe_books(books);
+        assert_eq!(books.len(), 2);
+        let huckfinn = books
+            .iter()
+            .find(|book| book.title == "The Adventures of Huckleberry Finn")
+            .unwrap();
+        assert_eq!(huckfinn.tracks.len(), 5);
+        assert_eq!(huckfinn.total_tracks, 5);
+        assert_eq!(
+            huckfinn.total_duration,
+            Some(Duration::from_millis(136_620))
+        );
+
+        // a copy of a single file book keeps its chapters
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("The Call of the Wild", dir.path());
+        let original = parse_book("../TestData/The Call of the Wild")
+            .unwrap()
+            .unwrap();
+        assert!(!original.chapters.is_empty());
+        let books = dedupe_books(vec![
+            original.clone(),
+            parse_book(dir.path()).unwrap().unwrap(),
+        ]);
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].tracks.len(), 1);
+        assert_eq!(books[0].chapters, original.chapters);
+    }
+
+    #[test]
+    fn test_group_split_books() {
+        let dir = tempfile::tempdir().unwrap();
+        for part in ["Disc 1", "Disc 2"] {
+            let name = format!("Around the World in Eighty Days ({})", part);
+            copy_book(&name, &dir.path().join(&name));
+        }
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        let books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books.len(), 3);
+
+        let books = group_split_books(books);
+        assert_eq!(books.len(), 2);
+        let book = books
+            .iter()
+            .find(|book| book.title == "Around the World in Eighty Days")
+            .unwrap();
+        assert_eq!(book.discs, Some(2));
+        assert_eq!(book.total_tracks, 4);
+        let positions: Vec<_> = book
+            .tracks
+            .iter()
+            .map(|track| (track.disc, track.track, track.title.as_str()))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (Some(1), 1, "Chapter 1"),
+                (Some(1), 2, "Chapter 2"),
+                (Some(2), 1, "Chapter 3"),
+                (Some(2), 2, "Chapter 4"),
+            ]
+        );
+
+        // parts of different editions are left untouched
+        let mut books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        let second = books
+            .iter_mut()
+            .find(|book| book.title.ends_with("(Disc 2)"))
+            .unwrap();
+        second.year = Some(1873);
+        let second = second.clone();
+        let books = group_split_books(books);
+        assert_eq!(books.len(), 3);
+        assert!(books.contains(&second));
+        assert!(books.iter().any(|book| book.title.ends_with("(Disc 1)")));
+    }
+
+    #[test]
+    fn test_filter_by_author() {
+        let books: Vec<Book> = ["Huckfinn", "Penguin Island", "Winnetou"]
+            .into_iter()
+            .map(|dir| {
+                parse_book(Path::new("../TestData").join(dir))
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        let titles = |matching| {
+            filter_by_author(&books, "mark twain", matching)
+                .map(|book| book.title.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles(NameMatch::Exact),
+            vec!["The Adventures of Huckleberry Finn"]
+        );
+        assert_eq!(
+            filter_by_author(&books, "twain", NameMatch::Exact).count(),
+            0
+        );
+        assert_eq!(
+            filter_by_author(&books, "TWAIN", NameMatch::Substring).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_group_by_author() {
+        let book = |title: &str, authors: &[&str]| {
+            authors
+                .iter()
+                .fold(Book::builder().title(title), |builder, author| {
+                    builder.author(*author)
+                })
+                .build()
+                .unwrap()
+        };
+        let books = vec![
+            book("The Gilded Age", &["Mark Twain", "Charles Dudley Warner"]),
+            book("The Adventures of Tom Sawyer", &["Mark Twain"]),
+            book("Beowulf", &[]),
+        ];
+
+        let grouped = group_by_author(books);
+        let titles: Vec<(&str, Vec<&str>)> = grouped
+            .iter()
+            .map(|(author, books)| {
+                let titles = books.iter().map(|book| book.title.as_str()).collect();
+                (author.as_str(), titles)
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("", vec!["Beowulf"]),
+                ("Charles Dudley Warner", vec!["The Gilded Age"]),
+                (
+                    "Mark Twain",
+                    vec!["The Gilded Age", "The Adventures of Tom Sawyer"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_disc_suffix() {
+        assert_eq!(split_disc_suffix("Dracula (Disc 1)"), Some(("Dracula", 1)));
+        assert_eq!(split_disc_suffix("Dracula [CD 12]"), Some(("Dracula", 12)));
+        assert_eq!(split_disc_suffix("Dracula - disc 3"), Some(("Dracula", 3)));
+        assert_eq!(split_disc_suffix("Dracula CD2"), Some(("Dracula", 2)));
+        assert_eq!(split_disc_suffix("Dracula"), None);
+        assert_eq!(split_disc_suffix("Abracadabra 2"), None);
+        assert_eq!(split_disc_suffix("(Disc 1)"), None);
+    }
+
+    #[test]
+    fn test_ignore_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        copy_book("Winnetou", &dir.path().join("tmp").join("Winnetou"));
+        std::fs::write(dir.path().join("tmp").join(IGNORE_MARKER), "").unwrap();
+
+        let books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "The Adventures of Huckleberry Finn");
+        assert_eq!(par_parse_all_books(dir.path().to_path_buf()).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_all_books_with_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        let author = dir.path().join("Karl May");
+        copy_book("Winnetou", &author.join("Winnetou"));
+        copy_book(
+            "Penguin Island",
+            &author.join("extras").join("Penguin Island"),
+        );
+
+        let titles = |max_depth| {
+            parse_all_books_with_depth(dir.path().to_path_buf(), max_depth)
+                .map(|book| book.unwrap().title)
+                .collect::<BTreeSet<_>>()
+        };
+        assert!(titles(0).is_empty());
+        assert_eq!(
+            titles(1),
+            BTreeSet::from(["The Adventures of Huckleberry Finn".to_string()])
+        );
+        assert_eq!(titles(2).len(), 2);
+        assert_eq!(titles(3).len(), 3);
+    }
+
+    #[test]
+    fn test_parse_all_books_deep_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deepest = dir.path().to_path_buf();
+        for _ in 0..500 {
+            deepest.push("d");
+        }
+        copy_book("Huckfinn", &deepest);
+
+        // run on a small stack, a recursive walk of the 500 levels wouldn't fit
+        let root = dir.path().to_path_buf();
+        let books = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                parse_all_books(root)
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "The Adventures of Huckleberry Finn");
+    }
+
+    #[test]
+    fn test_parse_all_books_with_progress() {
+        let mut visited = Vec::new();
+        let books: Vec<Book> = parse_all_books_with_progress(PathBuf::from("../TestData"), |dir| {
+            visited.push(dir.to_path_buf())
+        })
+        .map(Result::unwrap)
+        .collect();
+
+        let mut expected = vec![PathBuf::from("../TestData")];
+        for entry in std::fs::read_dir("../TestData").unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() && !entry.path().join(IGNORE_MARKER).exists() {
+                expected.push(entry.path());
+            }
+        }
+        visited.sort();
+        expected.sort();
+        assert_eq!(visited, expected);
+        // the top level holds a single book as well
+        assert_eq!(books.len(), visited.len());
+    }
+
+    #[test]
+    fn test_parse_all_books_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        copy_book("Winnetou", &dir.path().join("Winnetou"));
+
+        assert_eq!(
+            parse_all_books_strict(dir.path().to_path_buf())
+                .unwrap()
+                .len(),
+            2
+        );
+
+        std::fs::write(
+            dir.path().join("Winnetou").join("corrupt.mp3"),
+            b"not an mp3",
+        )
+        .unwrap();
+        let err = parse_all_books_strict(dir.path().to_path_buf()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::Unreadable { path, .. }) if path.ends_with("corrupt.mp3")
+        ));
+        // the lenient walk only skips the file
+        let books: Vec<Book> = parse_all_books(dir.path().to_path_buf())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books.len(), 2);
+    }
+
+    #[test]
+    fn test_symlink_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        let book_dir = dir.path().join("Huckfinn");
+        copy_book("Huckfinn", &book_dir);
+        std::os::unix::fs::symlink(dir.path(), book_dir.join("loop")).unwrap();
+        std::os::unix::fs::symlink(book_dir.join("self"), book_dir.join("self")).unwrap();
+
+        let books: Vec<Result<Book>> = parse_all_books(dir.path().to_path_buf()).collect();
+        assert_eq!(books.len(), 1);
+        assert!(books[0].is_ok());
+        assert_eq!(par_parse_all_books(dir.path().to_path_buf()).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_all_books_unreadable_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("Huckfinn", &dir.path().join("Huckfinn"));
+        let locked = dir.path().join("locked");
+        copy_book("Winnetou", &locked);
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // privileged users can read the directory anyway
+        let enforced = std::fs::read_dir(&locked).is_err();
+
+        let results: Vec<_> = parse_all_books(dir.path().to_path_buf()).collect();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(results.iter().any(
+            |res| matches!(res, Ok(book) if book.title == "The Adventures of Huckleberry Finn")
+        ));
+        if enforced {
+            assert_eq!(results.len(), 2);
+            assert_eq!(results.iter().filter(|res| res.is_err()).count(), 1);
+        }
+
+        let missing: Vec<_> = parse_all_books(dir.path().join("missing")).collect();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].is_err());
+    }
 }
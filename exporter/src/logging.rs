@@ -0,0 +1,29 @@
+use tracing::Level;
+
+/// Prints the warnings and debug messages of the crate to stderr, for applications that
+/// don't set up a `tracing` subscriber themselves.
+///
+/// Only available with the `logging` feature. Does nothing if a subscriber is installed
+/// already, so calling it more than once is fine.
+pub fn init_tracing(level: Level) {
+    let installed = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .try_init();
+    if installed.is_err() {
+        tracing::debug!("a tracing subscriber is installed already");
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_init_tracing_twice() {
+        init_tracing(Level::DEBUG);
+        init_tracing(Level::WARN);
+        tracing::warn!("still logging");
+    }
+}
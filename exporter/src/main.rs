@@ -1,3 +1,129 @@
-fn main() {
-    println!("Hello, world!");
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use exporter::{
+    books_to_table, dedupe_books, explain_file, export_csv, export_opml, init_tracing,
+    parse_all_books, parse_book, Book,
+};
+
+#[derive(Parser)]
+#[command(version, about = "Exports the metadata of an audiobook library")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scans a directory tree for books and prints the library
+    Scan {
+        #[arg(required_unless_present = "book")]
+        path: Option<PathBuf>,
+        /// Parse only this directory as one book, without looking into subdirectories
+        #[arg(long, conflicts_with = "path")]
+        book: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Shows which tag frame of each mp3 feeds which field of its book
+    Explain {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Yaml,
+    Table,
+    Opml,
+    Csv,
+}
+
+fn main() -> Result<ExitCode> {
+    color_eyre::install()?;
+    init_tracing(tracing::Level::INFO);
+
+    match Cli::parse().command {
+        Command::Scan {
+            path,
+            book,
+            format,
+            output,
+        } => {
+            let books: Box<dyn Iterator<Item = Result<Book>>> = match (path, book) {
+                (_, Some(dir)) => Box::new(std::iter::once(parse_book(&dir).and_then(|book| {
+                    book.ok_or_else(|| eyre!("no audio files in {:?}", dir.display()))
+                }))),
+                (Some(path), None) => parse_all_books(path),
+                (None, None) => unreachable!("clap requires a path or a book"),
+            };
+            scan(books, format, output)
+        }
+        Command::Explain { files } => {
+            let mut code = ExitCode::SUCCESS;
+            for file in files {
+                match explain_file(&file) {
+                    Ok(mapping) => print!("{}", mapping),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        code = ExitCode::FAILURE;
+                    }
+                }
+            }
+            Ok(code)
+        }
+    }
+}
+
+fn scan(
+    parsed: impl Iterator<Item = Result<Book>>,
+    format: Format,
+    output: Option<PathBuf>,
+) -> Result<ExitCode> {
+    let mut books = Vec::new();
+    let mut errors = Vec::new();
+    for res in parsed {
+        match res {
+            Ok(book) => books.push(book),
+            Err(e) => errors.push(e),
+        }
+    }
+    let books = dedupe_books(books);
+
+    let rendered = match format {
+        Format::Json => serde_json::to_string_pretty(&books)?,
+        Format::Yaml => serde_yaml::to_string(&books)?,
+        Format::Table => books_to_table(&books),
+        Format::Opml => export_opml(&books)?,
+        Format::Csv => {
+            let mut csv = Vec::new();
+            export_csv(&books, &mut csv)?;
+            String::from_utf8(csv)?
+        }
+    };
+    match output {
+        Some(output) => std::fs::write(&output, rendered)
+            .wrap_err(format!("can't write output: {:?}", output.display()))?,
+        None => io::stdout().write_all(rendered.as_bytes())?,
+    }
+
+    // errors are reported last so they don't get lost in the output
+    for e in &errors {
+        eprintln!("{:?}", e);
+    }
+    if errors.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
 }
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use tracing::warn;
+
+use crate::{Book, Track};
+
+impl Book {
+    /// Computes the new file name of every track from `template`, without renaming anything.
+    ///
+    /// The template can use `{track}`, `{disc}`, `{title}`, `{book}` and `{author}`, numbers
+    /// take a zero padded width like `{track:02}`. Characters not allowed in file names are
+    /// replaced by `_`, the extension and directory of the file are kept. Tracks that weren't
+    /// read from a file or already have the right name are left out, like the tracks of a
+    /// file split by a cue sheet, which can't be named after one of them.
+    ///
+    /// ```
+    /// let book = exporter::parse_book("../TestData/Huckfinn")?.unwrap();
+    /// let renames = book.plan_renames("{book} {track:02}");
+    /// assert!(renames[0].1.ends_with("The Adventures of Huckleberry Finn 01.mp3"));
+    /// # Ok::<(), color_eyre::Report>(())
+    /// ```
+    pub fn plan_renames(&self, template: &str) -> Vec<(PathBuf, PathBuf)> {
+        let mut tracks_per_file: HashMap<&Path, usize> = HashMap::new();
+        for track in &self.tracks {
+            *tracks_per_file.entry(&track.path).or_default() += 1;
+        }
+        self.tracks
+            .iter()
+            .filter(|track| !track.path.as_os_str().is_empty())
+            .filter(|track| tracks_per_file[track.path.as_path()] == 1)
+            .filter_map(|track| {
+                let mut name = expand_template(template, self, track);
+                if let Some(extension) = track.path.extension() {
+                    name.push('.');
+                    name.push_str(&extension.to_string_lossy());
+                }
+                let target = track.path.with_file_name(name);
+                (target != track.path).then(|| (track.path.clone(), target))
+            })
+            .collect()
+    }
+
+    /// Where the cover of the book belongs in a library laid out as `root/Author/Title`,
+    /// like `root/Mark Twain/Tom Sawyer/cover.jpg`.
+    ///
+    /// The directory names are cleaned like in [`Book::plan_renames`], a book without author
+    /// goes to `Unknown Author`. The file is a `cover.png` for a PNG cover, like
+    /// [`Book::write_cover`] names it.
+    pub fn cover_path(&self, root: &Path) -> PathBuf {
+        let author = match sanitize(&self.authors_joined(", ")) {
+            author if author.is_empty() => "Unknown Author".to_string(),
+            author => author,
+        };
+        let extension = self.cover.as_ref().map_or("jpg", |cover| cover.extension());
+        root.join(author)
+            .join(sanitize(&self.title))
+            .join(format!("cover.{}", extension))
+    }
+}
+
+/// Performs renames planned by [`Book::plan_renames`].
+///
+/// Either all files are renamed or none: existing files are never overwritten and if a
+/// rename fails, the ones done before are undone.
+pub fn apply_renames(renames: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let mut done: Vec<&(PathBuf, PathBuf)> = Vec::new();
+    for rename in renames {
+        let (from, to) = rename;
+        let res = if to.exists() {
+            Err(eyre!("{:?} already exists", to.display()))
+        } else {
+            std::fs::rename(from, to).wrap_err(format!(
+                "can't rename {:?} to {:?}",
+                from.display(),
+                to.display()
+            ))
+        };
+        if let Err(e) = res {
+            for (from, to) in done.into_iter().rev() {
+                if let Err(e) = std::fs::rename(to, from) {
+                    warn!("can't undo renaming {:?}: {}", from.display(), e);
+                }
+            }
+            return Err(e);
+        }
+        done.push(rename);
+    }
+    Ok(())
+}
+
+/// Replaces the placeholders of `template`, unknown ones are kept as they are.
+fn expand_template(template: &str, book: &Book, track: &Track) -> String {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start + 1..start + end];
+        let (field, width) = match placeholder.split_once(':') {
+            Some((field, width)) => (field, width.parse().unwrap_or(0)),
+            None => (placeholder, 0),
+        };
+        let number = |nr: Option<u32>| nr.map_or(String::new(), |nr| format!("{nr:0width$}"));
+        match field {
+            "track" => name.push_str(&number(Some(track.track))),
+            "disc" => name.push_str(&number(track.disc)),
+            "title" => name.push_str(&sanitize(&track.title)),
+            "book" => name.push_str(&sanitize(&book.title)),
+            "author" => name.push_str(&sanitize(&book.authors_joined(", "))),
+            _ => name.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    name
+}
+
+/// Replaces characters that aren't allowed in file names on Windows or Unix by `_`.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        // Windows drops trailing dots
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::Cover;
+
+    fn book(dir: &Path) -> Book {
+        Book::builder()
+            .title("The Adventures of Tom Sawyer")
+            .author("Mark Twain")
+            .track(
+                Track::builder("Chapter 1: Tom Plays, Fights, and Hides", 1)
+                    .path(dir.join("tom_sawyer_01.mp3"))
+                    .build(),
+            )
+            .track(
+                Track::builder("Chapter 2 / The Glorious Whitewasher", 2)
+                    .disc(1)
+                    .path(dir.join("tom_sawyer_02.mp3"))
+                    .build(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plan_renames() {
+        let dir = Path::new("/library/Tom Sawyer");
+        let renames = book(dir).plan_renames("{disc:02}{track:03} - {title}");
+        assert_eq!(
+            renames,
+            vec![
+                (
+                    dir.join("tom_sawyer_01.mp3"),
+                    dir.join("001 - Chapter 1_ Tom Plays, Fights, and Hides.mp3")
+                ),
+                (
+                    dir.join("tom_sawyer_02.mp3"),
+                    dir.join("01002 - Chapter 2 _ The Glorious Whitewasher.mp3")
+                ),
+            ]
+        );
+
+        let renames = book(dir).plan_renames("{author} - {book} {track} {unknown}");
+        assert_eq!(
+            renames[0].1,
+            dir.join("Mark Twain - The Adventures of Tom Sawyer 1 {unknown}.mp3")
+        );
+
+        // the tracks of a cue sheet share their file
+        let book = crate::parse_book("../TestData/The Sign of the Four")
+            .unwrap()
+            .unwrap();
+        assert!(book.tracks.len() > 1);
+        assert!(book.plan_renames("{track:02} - {title}").is_empty());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("AC/DC: Live"), "AC_DC_ Live");
+        assert_eq!(sanitize("The End..."), "The End");
+        assert_eq!(sanitize("Tab\there"), "Tab_here");
+    }
+
+    #[test]
+    fn test_apply_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tom_sawyer_01.mp3"), "1").unwrap();
+        std::fs::write(dir.path().join("tom_sawyer_02.mp3"), "2").unwrap();
+        let renames = book(dir.path()).plan_renames("{track:02}");
+
+        // the second target is taken, so the first rename is undone
+        std::fs::write(dir.path().join("02.mp3"), "taken").unwrap();
+        assert!(apply_renames(&renames).is_err());
+        assert!(dir.path().join("tom_sawyer_01.mp3").exists());
+        assert!(!dir.path().join("01.mp3").exists());
+
+        std::fs::remove_file(dir.path().join("02.mp3")).unwrap();
+        apply_renames(&renames).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("02.mp3")).unwrap(),
+            "2"
+        );
+        assert!(!dir.path().join("tom_sawyer_02.mp3").exists());
+    }
+
+    #[test]
+    fn test_cover_path() {
+        let root = Path::new("/library");
+        let book = Book::builder()
+            .title("The Strange Case of Dr Jekyll and Mr Hyde?")
+            .author("Stevenson/Osbourne")
+            .track(Track::builder("Chapter 1", 1).build())
+            .build()
+            .unwrap();
+        assert_eq!(
+            book.cover_path(root),
+            root.join("Stevenson_Osbourne")
+                .join("The Strange Case of Dr Jekyll and Mr Hyde_")
+                .join("cover.jpg")
+        );
+
+        let book = Book::builder()
+            .title("Anonymous")
+            .cover(Cover::new("image/png", Vec::new()))
+            .track(Track::builder("Chapter 1", 1).build())
+            .build()
+            .unwrap();
+        assert_eq!(
+            book.cover_path(root),
+            root.join("Unknown Author")
+                .join("Anonymous")
+                .join("cover.png")
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use serde::Deserialize;
+
+use crate::{Book, ParseError};
+
+/// The file name of the sidecar Audiobookshelf stores next to the audio files.
+const SIDECAR_FILE: &str = "metadata.json";
+
+/// The fields of an Audiobookshelf `metadata.json` the exporter knows about.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Sidecar {
+    title: Option<String>,
+    authors: Vec<String>,
+    narrators: Vec<String>,
+    /// Entries like `Discworld #3`, only the first is used.
+    series: Vec<String>,
+    description: Option<String>,
+    published_year: Option<String>,
+}
+
+/// Overlays the `metadata.json` in `dir` on `book`, fields set in the sidecar win.
+///
+/// Does nothing if there's no sidecar.
+pub(crate) fn apply_sidecar(book: &mut Book, dir: &Path) -> Result<(), ParseError> {
+    let path = dir.join(SIDECAR_FILE);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ParseError::unreadable(path, e)),
+    };
+    let sidecar: Sidecar = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| ParseError::unreadable(&path, e))?;
+
+    if let Some(title) = sidecar.title.filter(|title| !title.trim().is_empty()) {
+        book.title = title;
+    }
+    if !sidecar.authors.is_empty() {
+        book.author = sidecar.authors.into_iter().collect();
+    }
+    if !sidecar.narrators.is_empty() {
+        for track in &mut book.tracks {
+            track.reader.clone_from(&sidecar.narrators);
+        }
+    }
+    if let Some(series) = sidecar.series.first() {
+        let (name, index) = split_series(series);
+        book.series = Some(name.to_string());
+        book.series_index = index;
+    }
+    if let Some(description) = sidecar.description {
+        book.description = Some(description);
+    }
+    if let Some(year) = sidecar
+        .published_year
+        .and_then(|year| year.trim().parse().ok())
+    {
+        book.year = Some(year);
+    }
+    Ok(())
+}
+
+/// Splits `Discworld #3` into the series name and its index.
+fn split_series(series: &str) -> (&str, Option<f32>) {
+    match series.rsplit_once('#') {
+        Some((name, index)) => match index.trim().parse() {
+            Ok(index) => (name.trim(), Some(index)),
+            Err(_) => (series.trim(), None),
+        },
+        None => (series.trim(), None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::{parse_book, parse_book_with_options, ParseOptions};
+
+    #[test]
+    fn test_sidecar() {
+        let options = ParseOptions {
+            metadata_sidecar: true,
+            ..Default::default()
+        };
+        let book = parse_book_with_options("../TestData/Jekyll and Hyde", &options)
+            .unwrap()
+            .unwrap();
+        insta::assert_yaml_snapshot!(book);
+
+        // opt-in, the tags alone only know the album
+        let book = parse_book("../TestData/Jekyll and Hyde").unwrap().unwrap();
+        assert_eq!(book.title, "Jekyll and Hyde");
+        assert_eq!(book.year, None);
+    }
+
+    #[test]
+    fn test_split_series() {
+        assert_eq!(split_series("Discworld #3"), ("Discworld", Some(3.0)));
+        assert_eq!(split_series("The Expanse #1.5"), ("The Expanse", Some(1.5)));
+        assert_eq!(split_series("Sherlock Holmes"), ("Sherlock Holmes", None));
+        assert_eq!(split_series("Book #One"), ("Book #One", None));
+    }
+}
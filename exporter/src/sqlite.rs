@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use color_eyre::eyre::{Result, WrapErr};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::Book;
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS books (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    year INTEGER,
+    genre TEXT,
+    discs INTEGER,
+    total_tracks INTEGER NOT NULL,
+    total_duration REAL
+);
+
+CREATE TABLE IF NOT EXISTS authors (
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS readers (
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tracks (
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    track INTEGER NOT NULL,
+    disc INTEGER,
+    duration REAL
+);
+";
+
+const INSERT_BOOK: &str = "
+INSERT INTO books (title, year, genre, discs, total_tracks, total_duration)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+";
+
+const UPDATE_BOOK: &str = "
+UPDATE books SET genre = ?2, discs = ?3, total_tracks = ?4, total_duration = ?5
+WHERE id = ?1
+";
+
+/// Writes the books into the SQLite database at `db`, creating the tables if needed.
+///
+/// Books are identified by title and year, a book that is already stored gets updated.
+pub fn export_to_sqlite(books: impl Iterator<Item = Book>, db: &Path) -> Result<()> {
+    let mut conn =
+        Connection::open(db).wrap_err(format!("can't open database: {:?}", db.display()))?;
+    conn.execute_batch(SCHEMA)
+        .wrap_err("can't create database schema")?;
+
+    let tx = conn.transaction()?;
+    for book in books {
+        upsert_book(&tx, &book).wrap_err(format!("can't store book {:?}", book.title))?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn upsert_book(tx: &Transaction, book: &Book) -> Result<()> {
+    let total_duration = book.total_duration.map(|d| d.as_secs_f64());
+    // `IS` instead of `=` so books without a year match as well
+    let existing: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM books WHERE title = ?1 AND year IS ?2",
+            params![book.title, book.year],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let id = match existing {
+        Some(id) => {
+            tx.execute(
+                UPDATE_BOOK,
+                params![
+                    id,
+                    book.genre,
+                    book.discs,
+                    book.total_tracks,
+                    total_duration
+                ],
+            )?;
+            for table in ["authors", "readers", "tracks"] {
+                tx.execute(&format!("DELETE FROM {} WHERE book_id = ?1", table), [id])?;
+            }
+            id
+        }
+        None => {
+            tx.execute(
+                INSERT_BOOK,
+                params![
+                    book.title,
+                    book.year,
+                    book.genre,
+                    book.discs,
+                    book.total_tracks,
+                    total_duration
+                ],
+            )?;
+            tx.last_insert_rowid()
+        }
+    };
+
+    for author in &book.author {
+        tx.execute(
+            "INSERT INTO authors (book_id, name) VALUES (?1, ?2)",
+            params![id, author],
+        )?;
+    }
+    for reader in &book.reader() {
+        tx.execute(
+            "INSERT INTO readers (book_id, name) VALUES (?1, ?2)",
+            params![id, reader],
+        )?;
+    }
+    for track in &book.tracks {
+        tx.execute(
+            "INSERT INTO tracks (book_id, title, track, disc, duration) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                track.title,
+                track.track,
+                track.disc,
+                track.duration.map(|d| d.as_secs_f64())
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parse_all_books;
+
+    #[test]
+    fn test_export_to_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("library.sqlite");
+        let books = || parse_all_books(PathBuf::from("../TestData")).map(Result::unwrap);
+
+        // the second run updates the rows of the first one
+        export_to_sqlite(books(), &db).unwrap();
+        export_to_sqlite(books(), &db).unwrap();
+
+        let conn = Connection::open(&db).unwrap();
+        let huckfinn: Vec<i64> = conn
+            .prepare("SELECT id FROM books WHERE title = 'The Adventures of Huckleberry Finn'")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(huckfinn.len(), 1);
+
+        let tracks: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tracks WHERE book_id = ?1",
+                [huckfinn[0]],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tracks, 5);
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Result, WrapErr};
+use tokio::{fs, sync::mpsc, task};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tracing::warn;
+
+use crate::{parse_book, Book, IGNORE_MARKER};
+
+/// Like [`parse_all_books`](crate::parse_all_books), but walks the tree with `tokio::fs` and
+/// parses every book on the blocking thread pool.
+///
+/// The books arrive in the same order as from the synchronous version. Has to be called from
+/// within a tokio runtime, the walk runs on a spawned task.
+pub fn parse_all_books_async(path: PathBuf) -> impl Stream<Item = Result<Book>> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut visited = HashSet::new();
+        // a stack instead of recursion, children are pushed in reverse to keep the order
+        let mut stack = vec![path];
+        while let Some(dir) = stack.pop() {
+            let res = match read_sub_dirs(&dir, &mut visited).await {
+                Ok(Some(sub_dirs)) => {
+                    stack.extend(sub_dirs.into_iter().rev());
+                    task::spawn_blocking(move || parse_book(dir))
+                        .await
+                        .wrap_err("parsing task failed")
+                        .and_then(|res| res)
+                        .transpose()
+                }
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            };
+            if let Some(res) = res {
+                if tx.send(res).await.is_err() {
+                    // the stream was dropped
+                    return;
+                }
+            }
+        }
+    });
+
+    ReceiverStream::new(rx)
+}
+
+/// The directories in `dir`, `None` if `dir` was visited already or is ignored.
+async fn read_sub_dirs(dir: &Path, visited: &mut HashSet<PathBuf>) -> Result<Option<Vec<PathBuf>>> {
+    let canonical = fs::canonicalize(dir)
+        .await
+        .wrap_err(format!("can't read directory: {:?}", dir.display()))?;
+    if visited.contains(&canonical) {
+        warn!(
+            "Skipping {:?}, it leads to the already visited {:?}",
+            dir.display(),
+            canonical.display()
+        );
+        return Ok(None);
+    }
+    visited.insert(canonical);
+    if fs::try_exists(dir.join(IGNORE_MARKER))
+        .await
+        .unwrap_or(false)
+    {
+        tracing::debug!("ignoring {:?}", dir);
+        return Ok(None);
+    }
+
+    let mut entries = fs::read_dir(dir)
+        .await
+        .wrap_err(format!("can't read directory: {:?}", dir.display()))?;
+    let mut sub_dirs = Vec::new();
+    loop {
+        match entries.next_entry().await {
+            Ok(Some(entry)) => {
+                // `fs::metadata` follows symlinks, unlike the file type of the entry
+                if fs::metadata(entry.path())
+                    .await
+                    .is_ok_and(|meta| meta.is_dir())
+                {
+                    sub_dirs.push(entry.path());
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Error while collecting path: {:?}", &e);
+                break;
+            }
+        }
+    }
+    Ok(Some(sub_dirs))
+}
+
+#[cfg(test)]
+mod test {
+
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::parse_all_books;
+
+    #[tokio::test]
+    async fn test_parse_all_books_async() {
+        let books: Vec<Book> = parse_all_books_async(PathBuf::from("../TestData"))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let expected: Vec<Book> = parse_all_books(PathBuf::from("../TestData"))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(books, expected);
+    }
+
+    #[tokio::test]
+    async fn test_parse_all_books_async_missing_dir() {
+        let books: Vec<Result<Book>> = parse_all_books_async(PathBuf::from("../TestData/missing"))
+            .collect()
+            .await;
+        assert_eq!(books.len(), 1);
+        assert!(books[0].is_err());
+    }
+}
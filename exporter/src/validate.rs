@@ -0,0 +1,177 @@
+use std::{collections::BTreeSet, fmt};
+
+use serde::Serialize;
+
+use crate::Book;
+
+/// How much an [`Issue`] matters, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    /// Metadata that would be nice to have.
+    Info,
+    /// Metadata that's missing or looks wrong, the audio is fine.
+    Warning,
+    /// Audio is missing or there twice.
+    Error,
+}
+
+/// A problem with the integrity or metadata of a book, see [`Book::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Issue {
+    /// The track numbers of [`Book::missing_tracks`].
+    MissingTracks(Vec<u32>),
+    /// The disc numbers of [`Book::missing_discs`].
+    MissingDiscs(Vec<u32>),
+    /// The track numbers of [`Book::duplicate_tracks`].
+    DuplicateTracks(Vec<u32>),
+    NoAuthor,
+    NoYear,
+    /// The tracks aren't all read by the same people. Fine for a collaborative recording,
+    /// but it may also be two editions mixed up.
+    InconsistentReaders(BTreeSet<String>),
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::MissingTracks(_) | Issue::MissingDiscs(_) | Issue::DuplicateTracks(_) => {
+                Severity::Error
+            }
+            Issue::NoAuthor | Issue::InconsistentReaders(_) => Severity::Warning,
+            Issue::NoYear => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingTracks(tracks) => write!(f, "missing tracks {:?}", tracks),
+            Issue::MissingDiscs(discs) => write!(f, "missing discs {:?}", discs),
+            Issue::DuplicateTracks(tracks) => write!(f, "duplicate tracks {:?}", tracks),
+            Issue::NoAuthor => write!(f, "no author"),
+            Issue::NoYear => write!(f, "no year"),
+            Issue::InconsistentReaders(readers) => {
+                write!(f, "tracks read by different people {:?}", readers)
+            }
+        }
+    }
+}
+
+/// All issues of one book, most severe first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The severity of the worst issue, `None` for a clean book.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.iter().map(Issue::severity).max()
+    }
+
+    /// The issues of at least the given severity.
+    pub fn at_least(&self, severity: Severity) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(move |issue| issue.severity() >= severity)
+    }
+}
+
+impl Book {
+    /// Checks the book for missing or duplicate tracks and gaps in its metadata, as a
+    /// checklist for cleaning up the library.
+    pub fn validate(&self) -> ValidationReport {
+        // checked from the most to the least severe
+        let mut issues = Vec::new();
+        let missing = self.missing_tracks();
+        if !missing.is_empty() {
+            issues.push(Issue::MissingTracks(missing));
+        }
+        let missing = self.missing_discs();
+        if !missing.is_empty() {
+            issues.push(Issue::MissingDiscs(missing));
+        }
+        let duplicates = self.duplicate_tracks();
+        if !duplicates.is_empty() {
+            issues.push(Issue::DuplicateTracks(duplicates));
+        }
+        if self.author.is_empty() {
+            issues.push(Issue::NoAuthor);
+        }
+        if self
+            .tracks
+            .windows(2)
+            .any(|pair| pair[0].reader != pair[1].reader)
+        {
+            issues.push(Issue::InconsistentReaders(self.reader()));
+        }
+        if self.year.is_none() {
+            issues.push(Issue::NoYear);
+        }
+        ValidationReport { issues }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::{parse_book, Track};
+
+    #[test]
+    fn test_validate_clean() {
+        let book = parse_book("../TestData/Treasure Island").unwrap().unwrap();
+        let report = book.validate();
+        assert!(report.is_empty(), "{:?}", report);
+        assert_eq!(report.max_severity(), None);
+
+        // two discs, each numbered from 1
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        let report = book.validate();
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_validate_messy() {
+        let book = Book::builder()
+            .title("The Prince and the Pauper")
+            .expected_tracks(4)
+            .track(
+                Track::builder("Chapter 1", 1)
+                    .reader("John Greenman")
+                    .build(),
+            )
+            .track(
+                Track::builder("Chapter 2", 2)
+                    .reader("John Greenman")
+                    .build(),
+            )
+            .track(Track::builder("Chapter 2", 2).reader("Mark Smith").build())
+            .build()
+            .unwrap();
+        let report = book.validate();
+        assert_eq!(
+            report.issues,
+            vec![
+                Issue::MissingTracks(vec![3, 4]),
+                Issue::DuplicateTracks(vec![2]),
+                Issue::NoAuthor,
+                Issue::InconsistentReaders(BTreeSet::from([
+                    "John Greenman".to_string(),
+                    "Mark Smith".to_string()
+                ])),
+                Issue::NoYear,
+            ]
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+        assert_eq!(report.at_least(Severity::Warning).count(), 4);
+        assert_eq!(report.issues[0].to_string(), "missing tracks [3, 4]");
+    }
+}
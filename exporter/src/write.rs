@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use id3::{Tag, TagLike, Timestamp};
+
+use crate::{Book, Track};
+
+impl Book {
+    /// Writes the title, album, album artist, track, disc and year of the book back into the
+    /// tags of its files, for fixing tags after [`Book::validate`].
+    ///
+    /// mp3 files get ID3 tags, m4b files MP4 tags, other formats fail before anything is
+    /// written. Values the book doesn't know are left as they are, and files whose tags are
+    /// already right aren't touched, so writing twice changes nothing. With `backup`, each
+    /// file is copied to `<file>.bak` before its first change, an existing backup is kept.
+    ///
+    /// A file holding several tracks, split by a cue sheet, only gets the fields of the book.
+    pub fn write_tags(&self, backup: bool) -> Result<()> {
+        let mut tracks_per_file: HashMap<&Path, usize> = HashMap::new();
+        for track in &self.tracks {
+            *tracks_per_file.entry(&track.path).or_default() += 1;
+        }
+        // the formats are checked first, so an unsupported file doesn't leave a half written
+        // book behind
+        let mut files: Vec<(&Track, TagFormat)> = Vec::new();
+        for track in &self.tracks {
+            let format = TagFormat::of(&track.path)?;
+            if !track.path.as_os_str().is_empty()
+                && !files.iter().any(|(other, _)| other.path == track.path)
+            {
+                files.push((track, format));
+            }
+        }
+
+        for (track, format) in files {
+            let path = &track.path;
+            let track = (tracks_per_file[path.as_path()] == 1).then_some(track);
+            match format {
+                TagFormat::Id3 => write_id3(self, track, path, backup),
+                TagFormat::Mp4 => write_mp4(self, track, path, backup),
+            }
+            .wrap_err(format!("can't write tags: {:?}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of tag a file gets, like [`parse_reader`](crate::parse_reader) picks the reader.
+#[derive(Debug, Clone, Copy)]
+enum TagFormat {
+    Id3,
+    Mp4,
+}
+
+impl TagFormat {
+    fn of(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("m4b" | "m4a" | "mp4") => Ok(TagFormat::Mp4),
+            Some("flac" | "ogg" | "oga" | "opus" | "mka" | "webm") => Err(eyre!(
+                "can't write tags of {:?}, only ID3 and MP4 are supported",
+                path.display()
+            )),
+            _ => Ok(TagFormat::Id3),
+        }
+    }
+}
+
+/// Copies `path` to `<path>.bak`, unless an earlier write already did.
+fn back_up(path: &Path) -> Result<()> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    if !backup.exists() {
+        std::fs::copy(path, &backup)
+            .wrap_err(format!("can't back up to {:?}", backup.display()))?;
+    }
+    Ok(())
+}
+
+/// Writes the ID3 tag of `path`, `track` is `None` for a file of several tracks.
+fn write_id3(book: &Book, track: Option<&Track>, path: &Path, backup: bool) -> Result<()> {
+    let old = match Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut tag = old.clone();
+    tag.set_album(&book.title);
+    if !book.author.is_empty() {
+        tag.set_album_artist(book.authors_joined("; "));
+    }
+    if let Some(track) = track {
+        tag.set_title(&track.title);
+        tag.set_track(track.track);
+        if let Some(disc) = track.disc {
+            tag.set_disc(disc);
+        }
+    }
+    if let Some(total) = book.expected_tracks {
+        tag.set_total_tracks(total);
+    }
+    if let Some(discs) = book.discs {
+        tag.set_total_discs(discs);
+    }
+    // read like the parser does, TYER before TDRC, so a full date of the right year stays
+    let year = tag
+        .year()
+        .or_else(|| tag.date_recorded().map(|date| date.year));
+    match book.year {
+        Some(new) if year != Some(new) && tag.version() == id3::Version::Id3v24 => {
+            tag.remove("TYER");
+            tag.set_date_recorded(Timestamp {
+                year: new,
+                month: None,
+                day: None,
+                hour: None,
+                minute: None,
+                second: None,
+            });
+        }
+        Some(new) if year != Some(new) => tag.set_year(new),
+        _ => {}
+    }
+
+    if tag == old {
+        return Ok(());
+    }
+    if backup {
+        back_up(path)?;
+    }
+    let version = tag.version();
+    tag.write_to_path(path, version)?;
+    Ok(())
+}
+
+/// Writes the MP4 tag of `path`, `track` is `None` for a file of several tracks.
+fn write_mp4(book: &Book, track: Option<&Track>, path: &Path, backup: bool) -> Result<()> {
+    let number = |n: u32| u16::try_from(n).wrap_err(format!("{} is too large for MP4", n));
+    // only the tag is written, the audio properties aren't needed
+    let config = mp4ameta::ReadConfig {
+        read_audio_info: false,
+        ..mp4ameta::ReadConfig::DEFAULT
+    };
+    let old = mp4ameta::Tag::read_with_path(path, &config)?;
+    let mut tag = old.clone();
+    tag.set_album(&book.title);
+    if !book.author.is_empty() {
+        tag.set_album_artist(book.authors_joined("; "));
+    }
+    if let Some(track) = track {
+        tag.set_title(&track.title);
+        tag.set_track_number(number(track.track)?);
+        if let Some(disc) = track.disc {
+            tag.set_disc_number(number(disc)?);
+        }
+    }
+    if let Some(total) = book.expected_tracks {
+        tag.set_total_tracks(number(total)?);
+    }
+    if let Some(discs) = book.discs {
+        tag.set_total_discs(number(discs)?);
+    }
+    let year = tag
+        .year()
+        .and_then(|date| date.get(..4)?.parse::<i32>().ok());
+    if let Some(new) = book.year.filter(|new| year != Some(*new)) {
+        tag.set_year(new.to_string());
+    }
+
+    if tag == old {
+        return Ok(());
+    }
+    if backup {
+        back_up(path)?;
+    }
+    tag.write_to_path(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::{parse_book, test::copy_book};
+
+    #[test]
+    fn test_write_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("The Moonstone", dir.path());
+        let mut book = parse_book(dir.path()).unwrap().unwrap();
+        book.title = "The Moonstone: A Romance".to_string();
+        book.author.insert("W. Collins".to_string());
+        book.year = Some(1868);
+        book.tracks[1].title = "Second Period".to_string();
+        book.write_tags(true).unwrap();
+
+        let written = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(written.title, book.title);
+        assert_eq!(written.author, book.author);
+        assert_eq!(written.year, Some(1868));
+        assert_eq!(written.expected_tracks, Some(2));
+        let tracks: Vec<(&str, u32)> = written
+            .tracks
+            .iter()
+            .map(|track| (track.title(), track.track()))
+            .collect();
+        assert_eq!(
+            tracks,
+            vec![(book.tracks[0].title(), 1), ("Second Period", 2)]
+        );
+
+        // the backup is the original, writing again changes nothing
+        let path = dir.path().join("moonstone_01_collins_64kb.mp3");
+        let backup = dir.path().join("moonstone_01_collins_64kb.mp3.bak");
+        assert_eq!(
+            std::fs::read(&backup).unwrap(),
+            std::fs::read("../TestData/The Moonstone/moonstone_01_collins_64kb.mp3").unwrap()
+        );
+        let before = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+        written.write_tags(true).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn test_write_mp4_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        copy_book("The Call of the Wild", dir.path());
+        let mut book = parse_book(dir.path()).unwrap().unwrap();
+        book.title = "The Call of the Wild (Illustrated)".to_string();
+        book.year = Some(1912);
+        book.write_tags(false).unwrap();
+
+        let written = parse_book(dir.path()).unwrap().unwrap();
+        assert_eq!(written.year, Some(1912));
+        assert_eq!(written.author, book.author);
+        assert_eq!(written.title, book.title);
+        assert_eq!(written.chapters, book.chapters);
+        assert!(!dir.path().join("call_of_the_wild_london.m4b.bak").exists());
+
+        let before = std::fs::read(dir.path().join("call_of_the_wild_london.m4b")).unwrap();
+        written.write_tags(false).unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("call_of_the_wild_london.m4b")).unwrap(),
+            before
+        );
+    }
+
+    #[test]
+    fn test_write_tags_unsupported() {
+        let book = parse_book("../TestData/The Invisible Man")
+            .unwrap()
+            .unwrap();
+        let err = book.write_tags(true).unwrap_err();
+        assert!(err.to_string().contains("only ID3 and MP4"), "{}", err);
+    }
+}